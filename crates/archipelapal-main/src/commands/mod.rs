@@ -0,0 +1,4 @@
+pub mod saves;
+pub mod settings;
+pub mod simulate;
+pub mod validate;
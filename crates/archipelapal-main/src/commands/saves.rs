@@ -0,0 +1,268 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+use clap::{Args, Subcommand};
+
+use crate::{
+    defs::{
+        game_state::FullGameState,
+        paths::AppPaths,
+        save_file::{backup_path, corrupt_paths, SaveFile, SAVE_BACKUP_COUNT},
+        save_lock::SaveLock,
+    },
+    get_user_input,
+    utils::get_region_from_loc_id,
+};
+
+#[derive(Subcommand)]
+pub enum SavesCommand {
+    /// List all save files
+    List,
+    /// Show a summary of a save file
+    Show {
+        #[clap(flatten)]
+        selector: SaveSelector,
+
+        /// Print the whole save as JSON instead
+        #[clap(long)]
+        json: bool,
+    },
+    /// Delete a save file, along with its backups and journal
+    Delete {
+        #[clap(flatten)]
+        selector: SaveSelector,
+
+        /// Don't ask for confirmation
+        #[clap(long, short)]
+        yes: bool,
+    },
+}
+
+/// Picks a single save. The team and slot are only needed when a seed has more than one save.
+#[derive(Args)]
+pub struct SaveSelector {
+    pub seed: String,
+
+    #[clap(long)]
+    pub team: Option<i32>,
+
+    #[clap(long)]
+    pub slot: Option<i32>,
+}
+
+pub struct SaveEntry {
+    pub path: PathBuf,
+    pub save: Result<SaveFile>,
+}
+
+pub fn run_saves_command(command: SavesCommand) -> Result<()> {
+    match command {
+        SavesCommand::List => list_saves(),
+        SavesCommand::Show { selector, json } => {
+            let (path, save) = find_save(&selector)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&save)?);
+            } else {
+                print_save_summary(&path, &save);
+            }
+            Ok(())
+        }
+        SavesCommand::Delete { selector, yes } => delete_save(&selector, yes),
+    }
+}
+
+/// Every save file in the saves directory, whether or not it can be read
+pub fn load_all_saves() -> Result<Vec<SaveEntry>> {
+    let saves_dir = AppPaths::get().saves_dir();
+    if !saves_dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut entries = fs::read_dir(&saves_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("save-file-") && name.ends_with(".json"))
+        })
+        .map(|path| {
+            let save = SaveFile::read_from(&path);
+            SaveEntry { path, save }
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(entries)
+}
+
+pub fn find_save(selector: &SaveSelector) -> Result<(PathBuf, SaveFile)> {
+    let mut matches = load_all_saves()?
+        .into_iter()
+        .filter_map(|entry| Some((entry.path, entry.save.ok()?)))
+        .filter(|(_, save)| {
+            save.seed == selector.seed
+                && selector.team.map_or(true, |team| save.team == team)
+                && selector.slot.map_or(true, |slot| save.slot_id == slot)
+        })
+        .collect::<Vec<_>>();
+
+    match matches.len() {
+        0 => bail!("No save found for seed {}", selector.seed),
+        1 => Ok(matches.remove(0)),
+        _ => {
+            let candidates = matches
+                .iter()
+                .map(|(_, save)| format!("--team {} --slot {}", save.team, save.slot_id))
+                .collect::<Vec<_>>()
+                .join(", ");
+            bail!(
+                "Seed {} has more than one save, pick one with: {candidates}",
+                selector.seed
+            )
+        }
+    }
+}
+
+fn list_saves() -> Result<()> {
+    let entries = load_all_saves()?;
+    if entries.is_empty() {
+        println!(
+            "No saves found in {}",
+            AppPaths::get().saves_dir().display()
+        );
+        return Ok(());
+    }
+
+    for entry in entries {
+        let file_name = entry.path.file_name().unwrap_or_default().to_string_lossy();
+        match entry.save {
+            Ok(save) => {
+                let chests = save.map.map.values().flatten();
+                let total = chests.clone().count();
+                let checked = chests.filter(|chest| chest.checked).count();
+                let last_active = save
+                    .last_active
+                    .map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+                    .unwrap_or_else(|| "never".to_string());
+                println!(
+                    "{file_name}: seed {}, team {}, slot {}, {checked}/{total} checked, \
+                    last active {last_active}",
+                    save.seed, save.team, save.slot_id
+                );
+            }
+            Err(e) => println!("{file_name}: unreadable ({e})"),
+        }
+    }
+
+    Ok(())
+}
+
+fn print_save_summary(path: &Path, save: &SaveFile) {
+    let player = &save.player;
+    let last_active = save
+        .last_active
+        .map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|| "never".to_string());
+
+    println!("Save: {}", path.display());
+    println!(
+        "Seed {}, team {}, slot {}",
+        save.seed, save.team, save.slot_id
+    );
+    println!("Last active: {last_active}");
+    println!();
+
+    let keys = player.get_accessible_regions();
+    let mut regions = save.map.map.keys().copied().collect::<Vec<_>>();
+    regions.sort();
+
+    println!("{:<10} {:>9}  Key", "Region", "Checked");
+    for region in regions {
+        let chests = &save.map.map[&region];
+        let checked = chests.iter().filter(|chest| chest.checked).count();
+        let name = match region {
+            0 => "Hub".to_string(),
+            region => region.to_string(),
+        };
+        // Same as the gameplay loop, which only counts regions we hold the key to
+        let key = match keys.contains(&region) {
+            true => "held",
+            false => "missing",
+        };
+        let exploring = if region == player.currently_exploring_region {
+            " (exploring)"
+        } else {
+            ""
+        };
+        println!(
+            "{name:<10} {:>9}  {key}{exploring}",
+            format!("{checked}/{}", chests.len())
+        );
+    }
+    println!();
+
+    let num_goal = save
+        .num_goal
+        .map(|num_goal| num_goal.to_string())
+        .unwrap_or_else(|| "?".to_string());
+    println!("Goal items: {}/{num_goal}", player.get_num_goal_items());
+    println!(
+        "Speed modifier: {:.2} ({} boosts)",
+        player.speed_modifier,
+        player.get_num_boosts()
+    );
+    println!();
+
+    println!("Pending hints: {}", save.source_hint_queue.len());
+    let mut hints = save.source_hint_queue.iter().collect::<Vec<_>>();
+    hints.sort_by_key(|hint| hint.item.location);
+    for hint in hints {
+        let location = hint.item.location;
+        let receiver = match save.player_names.get(&hint.receiving_player) {
+            Some(name) => format!("{name} (slot {})", hint.receiving_player),
+            None => format!("slot {}", hint.receiving_player),
+        };
+        println!(
+            "  Location {location} (region {}): item {} for {receiver}",
+            get_region_from_loc_id(location as u32),
+            hint.item.item
+        );
+    }
+}
+
+fn delete_save(selector: &SaveSelector, yes: bool) -> Result<()> {
+    let (path, save) = find_save(selector)?;
+
+    // Makes sure the save isn't being played right now
+    let _save_lock = SaveLock::acquire(&path)?;
+
+    if !yes {
+        let answer = get_user_input(&format!(
+            "Delete save for seed {}, team {}, slot {}? This can't be undone! [y/N] ",
+            save.seed, save.team, save.slot_id
+        ))?;
+        if !answer.eq_ignore_ascii_case("y") {
+            println!("Not deleting anything");
+            return Ok(());
+        }
+    }
+
+    let journal = FullGameState::make_journal_file_name(&save.seed, save.team, save.slot_id);
+    let files = [path.clone(), journal]
+        .into_iter()
+        .chain((1..=SAVE_BACKUP_COUNT).map(|n| backup_path(&path, n)))
+        .chain(corrupt_paths(&path).take_while(|corrupt| corrupt.exists()));
+
+    for file in files {
+        if file.exists() {
+            fs::remove_file(&file)?;
+            println!("Deleted {}", file.display());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use anyhow::{bail, Result};
+use clap::Subcommand;
+use serde_json::Value;
+
+use crate::{
+    defs::{paths::AppPaths, secret, user_settings::UserSettings},
+    get_user_input,
+};
+
+#[derive(Subcommand)]
+pub enum SettingsCommand {
+    /// Show the current user settings
+    Show,
+    /// Forget a connection profile
+    RemoveProfile { name: String },
+    /// Forget all user settings, including the key remembered passwords are encrypted with
+    Reset {
+        /// Don't ask for confirmation
+        #[clap(long, short)]
+        yes: bool,
+    },
+}
+
+pub fn run_settings_command(command: SettingsCommand) -> Result<()> {
+    let settings_file = AppPaths::get().user_settings_file();
+
+    match command {
+        SettingsCommand::Show => {
+            let user_settings = UserSettings::load_or_default();
+            println!("Settings file: {}", settings_file.display());
+            println!(
+                "Bot config file: {}",
+                AppPaths::get().bot_config_file().display()
+            );
+            let mut shown = serde_json::to_value(&user_settings)?;
+            redact_passwords(&mut shown);
+            println!("{}", serde_json::to_string_pretty(&shown)?);
+        }
+        SettingsCommand::RemoveProfile { name } => {
+            let mut user_settings = UserSettings::load_or_default();
+            if user_settings.profiles.remove(&name).is_none() {
+                bail!("There's no profile named \"{name}\"");
+            }
+            if user_settings.last_used_profile.as_ref() == Some(&name) {
+                user_settings.last_used_profile = None;
+            }
+
+            user_settings.save()?;
+            println!("Removed profile \"{name}\"");
+        }
+        SettingsCommand::Reset { yes } => {
+            if !yes {
+                let answer = get_user_input("Reset all user settings? [y/N] ")?;
+                if !answer.eq_ignore_ascii_case("y") {
+                    println!("Not resetting anything");
+                    return Ok(());
+                }
+            }
+
+            UserSettings::default().save()?;
+            secret::delete_key()?;
+            println!("Reset {}", settings_file.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Remembered passwords are encrypted, but the ciphertext still doesn't belong on screen
+fn redact_passwords(settings: &mut Value) {
+    let Some(profiles) = settings.get_mut("profiles").and_then(Value::as_object_mut) else {
+        return;
+    };
+    for profile in profiles.values_mut() {
+        if let Some(password) = profile.get_mut("password").filter(|p| !p.is_null()) {
+            *password = Value::from("<redacted>");
+        }
+    }
+}
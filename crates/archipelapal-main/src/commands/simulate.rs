@@ -0,0 +1,172 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::{bail, Result};
+use clap::Args;
+use rand::{seq::SliceRandom, thread_rng};
+use tokio::sync::RwLock;
+
+use crate::{
+    defs::{
+        bot_config::BotConfig,
+        game_state::{FullGameState, GameMap},
+        items::Item,
+        lib::{ArchipelaPalSlotData, ItemID, LocationID},
+        paths::AppPaths,
+        save_file::SaveFile,
+    },
+    processes::game_playing_thread::calculate_wait_duration,
+    utils::get_region_from_loc_id,
+};
+
+const KEY_ITEM_BASE: ItemID = 0x020000;
+const GOAL_ITEM: ItemID = 0x010001;
+const SPEED_BOOST_ITEM: ItemID = 0x04000001;
+const JUNK_ITEM: ItemID = 0x000001;
+
+#[derive(Args)]
+pub struct SimulateArgs {
+    /// Number of regions, including the hub
+    #[clap(long, default_value_t = 5)]
+    num_regions: u8,
+
+    /// Chests in each region
+    #[clap(long, default_value_t = 10)]
+    chests_per_region: u8,
+
+    /// Goal items needed
+    #[clap(long, default_value_t = 5)]
+    num_goal: u16,
+
+    /// Speed boosts placed in the world
+    #[clap(long, default_value_t = 10)]
+    num_boosts: u16,
+
+    #[clap(long, default_value_t = 60)]
+    min_wait_time: u16,
+
+    #[clap(long, default_value_t = 180)]
+    max_wait_time: u16,
+}
+
+pub async fn run_simulate_command(args: SimulateArgs) -> Result<()> {
+    if args.num_regions == 0 || args.chests_per_region == 0 {
+        bail!("Need at least one region with at least one chest");
+    }
+    if args.min_wait_time > args.max_wait_time {
+        bail!("--min-wait-time can't be more than --max-wait-time");
+    }
+
+    let config = ArchipelaPalSlotData {
+        min_wait_time: args.min_wait_time,
+        max_wait_time: args.max_wait_time,
+        num_goal: args.num_goal,
+        slot_name: "Simulated".to_string(),
+        num_regions: args.num_regions,
+        chests_per_region_list: vec![args.chests_per_region; args.num_regions as usize],
+        game_theme: 0,
+    };
+
+    let map = GameMap::new_from_config(&config);
+    let placements = place_items(&map, &args)?;
+
+    // Routing and timing come from the bot config, same as when playing
+    let bot_config = BotConfig::load(&AppPaths::get().bot_config_file())?;
+    let timing_model = bot_config.timing.model;
+
+    let mut game_state = FullGameState::from(SaveFile::default());
+    game_state.bot_config = Arc::new(RwLock::new(bot_config));
+    *game_state.map.write().await = map;
+    // The hub is always open
+    game_state
+        .player
+        .write()
+        .await
+        .inventory
+        .insert(KEY_ITEM_BASE, 1);
+
+    let mut elapsed = Duration::ZERO;
+    let mut checks = 0;
+    loop {
+        let speed_modifier = game_state.player.read().await.speed_modifier;
+        let wait_time = timing_model.sample(
+            &mut thread_rng(),
+            config.min_wait_time,
+            config.max_wait_time,
+        );
+        elapsed += calculate_wait_duration(wait_time, speed_modifier, config.min_wait_time);
+
+        let Some(loc_id) = game_state.tick_game_state().await else {
+            println!("Stuck after {checks} checks, with nothing left to check!");
+            break;
+        };
+        checks += 1;
+
+        let item_id = placements[&loc_id];
+        let mut player = game_state.player.write().await;
+        *player.inventory.entry(item_id).or_insert(0) += 1;
+        if let Some(Item::Key(region)) = Item::from_id(item_id) {
+            log::info!("Found key {region} after {checks} checks");
+        }
+        player.set_speed_modifier();
+
+        if player.get_num_goal_items() >= config.num_goal {
+            break;
+        }
+    }
+
+    let player = game_state.player.read().await;
+    let total_checks = placements.len();
+    let hours = elapsed.as_secs_f32() / 3600.0;
+    println!("Checks made: {checks}/{total_checks}");
+    println!(
+        "Goal items: {}/{}",
+        player.get_num_goal_items(),
+        config.num_goal
+    );
+    println!("Final speed modifier: {:.2}", player.speed_modifier);
+    println!("Simulated play time: {hours:.1} hours");
+
+    Ok(())
+}
+
+/// Places every item in a chest, making sure each key is reachable before the region it opens
+fn place_items(map: &GameMap, args: &SimulateArgs) -> Result<HashMap<LocationID, ItemID>> {
+    let mut rng = thread_rng();
+    let mut open_chests = map
+        .map
+        .values()
+        .flatten()
+        .map(|chest| chest.full_id)
+        .collect::<Vec<_>>();
+    let total_chests = open_chests.len();
+
+    let num_keys = args.num_regions as usize - 1;
+    if num_keys + args.num_goal as usize + args.num_boosts as usize > total_chests {
+        bail!("Not enough chests ({total_chests}) for all the keys, goal items and boosts");
+    }
+
+    let mut placements = HashMap::new();
+    for key_region in 1..args.num_regions {
+        let reachable = open_chests
+            .iter()
+            .enumerate()
+            .filter(|(_, loc_id)| get_region_from_loc_id(**loc_id) < key_region)
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+        let Some(&idx) = reachable.choose(&mut rng) else {
+            bail!("No room left to place key {key_region}");
+        };
+
+        let loc_id = open_chests.swap_remove(idx);
+        placements.insert(loc_id, KEY_ITEM_BASE + key_region as ItemID);
+    }
+
+    open_chests.shuffle(&mut rng);
+    let other_items = vec![GOAL_ITEM; args.num_goal as usize]
+        .into_iter()
+        .chain(vec![SPEED_BOOST_ITEM; args.num_boosts as usize])
+        .chain(std::iter::repeat(JUNK_ITEM));
+    placements.extend(open_chests.into_iter().zip(other_items));
+
+    Ok(placements)
+}
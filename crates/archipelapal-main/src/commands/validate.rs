@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+
+use crate::{
+    defs::{save_file::SaveFile, save_file::CURRENT_SAVE_FORMAT_VERSION},
+    utils::get_region_from_loc_id,
+};
+
+#[derive(Args)]
+pub struct ValidateArgs {
+    /// Path to the save file to check
+    path: PathBuf,
+}
+
+pub fn run_validate_command(args: ValidateArgs) -> Result<()> {
+    let file_str = std::fs::read_to_string(&args.path)
+        .with_context(|| format!("Could not read {}", args.path.display()))?;
+    let save = SaveFile::from_json_str(&file_str).context("Save file is not readable")?;
+
+    let problems = find_problems(&save);
+    if !problems.is_empty() {
+        for problem in &problems {
+            println!("- {problem}");
+        }
+        bail!(
+            "Found {} problem(s) in {}",
+            problems.len(),
+            args.path.display()
+        );
+    }
+
+    println!(
+        "{} is a valid save (format version {CURRENT_SAVE_FORMAT_VERSION}) for seed {}, team {}, slot {}",
+        args.path.display(),
+        save.seed,
+        save.team,
+        save.slot_id
+    );
+
+    Ok(())
+}
+
+/// Everything that doesn't add up in a save, which would otherwise only show up mid-game
+fn find_problems(save: &SaveFile) -> Vec<String> {
+    let mut problems = vec![];
+
+    if save.seed.is_empty() {
+        problems.push("Save has no seed".to_string());
+    }
+
+    if save.map.map.is_empty() {
+        problems.push("Game map has no regions".to_string());
+    }
+
+    for (region, chests) in &save.map.map {
+        for chest in chests {
+            if chest.region != *region || get_region_from_loc_id(chest.full_id) != *region {
+                problems.push(format!(
+                    "Chest {} is listed under region {region}, but belongs to region {}",
+                    chest.full_id,
+                    get_region_from_loc_id(chest.full_id)
+                ));
+            }
+        }
+    }
+
+    let exploring = save.player.currently_exploring_region;
+    if !save.map.map.contains_key(&exploring) {
+        problems.push(format!(
+            "Currently exploring region {exploring}, which isn't on the map"
+        ));
+    }
+
+    let expected_speed = save.player.get_total_speed_modifier();
+    if (save.player.speed_modifier - expected_speed).abs() > f32::EPSILON {
+        problems.push(format!(
+            "Speed modifier is {}, but the inventory gives {expected_speed}",
+            save.player.speed_modifier
+        ));
+    }
+
+    for hint in &save.source_hint_queue {
+        if hint.item.player != save.slot_id {
+            problems.push(format!(
+                "Hinted location {} is in player {}'s world, not ours",
+                hint.item.location, hint.item.player
+            ));
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::defs::{chest::Chest, game_state::GameMap};
+
+    use super::*;
+
+    fn valid_save() -> SaveFile {
+        let mut map = GameMap::default();
+        for loc_id in [0x03000001, 0x03010001] {
+            let chest = Chest::new_from_id(loc_id);
+            map.map.entry(chest.region).or_default().push(chest);
+        }
+
+        SaveFile {
+            seed: "1234".to_string(),
+            map,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_valid_save() {
+        assert!(find_problems(&valid_save()).is_empty());
+    }
+
+    #[test]
+    fn test_find_problems() {
+        let mut save = valid_save();
+        save.seed.clear();
+        save.player.currently_exploring_region = 5;
+        // A region 1 chest filed under region 0
+        let misplaced = save.map.map.get_mut(&1).unwrap().pop().unwrap();
+        save.map.map.get_mut(&0).unwrap().push(misplaced);
+
+        let problems = find_problems(&save);
+        assert_eq!(problems.len(), 3, "{problems:?}");
+    }
+}
@@ -0,0 +1,205 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub const BOT_CONFIG_FILE: &str = "archipelapal.toml";
+
+/// Client-side tuning, read from a TOML file in the config directory. Unlike the slot data, none
+/// of this changes what the server thinks of us. Everything but `[startup]` is reloaded while the
+/// bot runs.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotConfig {
+    pub routing: RoutingConfig,
+    pub timing: TimingConfig,
+    pub chat: ChatConfig,
+    pub hints: HintConfig,
+    pub notifications: NotificationConfig,
+    pub console: ConsoleConfig,
+    pub copilot: CopilotConfig,
+    pub startup: StartupConfig,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RoutingConfig {
+    pub strategy: RoutingStrategy,
+    /// Head for hinted locations in our world as soon as they're reachable
+    pub prioritize_hints: bool,
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self {
+            strategy: RoutingStrategy::default(),
+            prioritize_hints: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutingStrategy {
+    /// Check everything in a region before moving on to the next one
+    #[default]
+    FinishRegion,
+    /// Check any reachable chest, in any region
+    Random,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimingConfig {
+    pub model: TimingModel,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimingModel {
+    /// Any time between the min and max is equally likely
+    #[default]
+    Uniform,
+    /// Times near the middle of the range are the most likely
+    Triangular,
+}
+
+impl TimingModel {
+    /// Picks a wait time in seconds, between `min` and `max`
+    pub fn sample(&self, rng: &mut impl Rng, min: u16, max: u16) -> u16 {
+        match self {
+            TimingModel::Uniform => rng.gen_range(min..=max),
+            TimingModel::Triangular => {
+                let a = rng.gen_range(min..=max) as u32;
+                let b = rng.gen_range(min..=max) as u32;
+                ((a + b) / 2) as u16
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChatConfig {
+    /// Said when we goal
+    pub goal_message: Option<String>,
+    /// Said when we run out of things to check. `{region}` stands in for the next locked region,
+    /// named after the game theme.
+    pub bk_message: Option<String>,
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            goal_message: Some("gg <3".to_string()),
+            bk_message: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HintConfig {
+    /// When BK'd, spend hint points on the key to the next locked region
+    pub spend_when_bk: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationConfig {
+    /// Ring the terminal bell when we BK
+    pub bell_on_bk: bool,
+    /// Ring the terminal bell when we find a key
+    pub bell_on_key: bool,
+    /// Ring the terminal bell when we goal
+    pub bell_on_goal: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConsoleConfig {
+    /// Let the operator console speed the bot up with `speed`. Only turn this on if the room's
+    /// host is fine with it. Slowing down is always allowed.
+    pub allow_speed_up: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CopilotConfig {
+    /// How long to wait for the operator to pick a chest in `--copilot` mode, before picking one
+    /// the usual way
+    pub timeout_secs: u64,
+}
+
+impl Default for CopilotConfig {
+    fn default() -> Self {
+        Self { timeout_secs: 30 }
+    }
+}
+
+/// Only read when the bot starts
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StartupConfig {
+    /// Same as `--catch-up`
+    pub catch_up: bool,
+}
+
+impl BotConfig {
+    /// Missing files are fine, and give the defaults
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let file_str = fs::read_to_string(path)?;
+        toml::from_str(&file_str).with_context(|| format!("Invalid bot config {}", path.display()))
+    }
+
+    /// Takes everything that's safe to change mid-game from `new`, and returns whether anything
+    /// that isn't was left out
+    pub fn apply_reload(&mut self, new: BotConfig) -> bool {
+        let needs_restart = self.startup != new.startup;
+        *self = BotConfig {
+            startup: self.startup.clone(),
+            ..new
+        };
+
+        needs_restart
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config: BotConfig = toml::from_str(
+            r#"
+            [routing]
+            strategy = "random"
+
+            [chat]
+            bk_message = "I'm stuck!"
+
+            [startup]
+            catch_up = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.routing.strategy, RoutingStrategy::Random);
+        assert!(config.routing.prioritize_hints);
+        assert_eq!(config.chat.goal_message.as_deref(), Some("gg <3"));
+        assert_eq!(config.chat.bk_message.as_deref(), Some("I'm stuck!"));
+
+        let mut running = BotConfig::default();
+        assert!(running.apply_reload(config));
+        assert_eq!(running.routing.strategy, RoutingStrategy::Random);
+        assert!(!running.startup.catch_up);
+
+        assert!(toml::from_str::<BotConfig>("[routing]\nstratgy = \"random\"").is_err());
+    }
+}
@@ -0,0 +1,14 @@
+use super::lib::LocationID;
+
+/// Commands from the operator to the gameplay loop
+#[derive(Clone, Debug, PartialEq)]
+pub enum ControlCommand {
+    Pause,
+    Resume,
+    /// Multiplies our speed modifier, on top of speed boosts
+    SetSpeedOverride(f32),
+    /// Says something in the room, which includes server commands like `!hint`
+    Say(String),
+    /// Checks this chest next, in answer to a `BotEvent::ChestChoiceOffered`
+    PickChest(LocationID),
+}
@@ -0,0 +1,84 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use anyhow::Result;
+use ap_rs::protocol::GameData;
+
+use super::paths::AppPaths;
+
+/// Item and location names for one game, from the server's DataPackage
+#[derive(Clone, Debug, Default)]
+pub struct GameNames {
+    pub items: HashMap<i32, String>,
+    pub locations: HashMap<i32, String>,
+}
+
+impl From<&GameData> for GameNames {
+    fn from(data: &GameData) -> Self {
+        Self {
+            items: invert(&data.item_name_to_id),
+            locations: invert(&data.location_name_to_id),
+        }
+    }
+}
+
+fn invert(name_to_id: &HashMap<String, i32>) -> HashMap<i32, String> {
+    name_to_id
+        .iter()
+        .map(|(name, id)| (*id, name.clone()))
+        .collect()
+}
+
+/// A game's data only changes when its checksum does, so that's all we key the cache on. `None`
+/// for anything that doesn't look like a checksum, since it ends up in a file name.
+fn cache_file(checksum: &str) -> Option<PathBuf> {
+    let is_hex = checksum
+        .chars()
+        .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c));
+    if checksum.is_empty() || !is_hex {
+        log::warn!("Not caching DataPackage with unexpected checksum {checksum:?}");
+        return None;
+    }
+
+    Some(
+        AppPaths::get()
+            .data_package_dir()
+            .join(format!("{checksum}.json")),
+    )
+}
+
+/// `None` if we haven't cached this checksum yet, or the cache is unreadable
+pub fn load_cached(checksum: &str) -> Option<GameData> {
+    let file_str = fs::read_to_string(cache_file(checksum)?).ok()?;
+    serde_json::from_str(&file_str)
+        .inspect_err(|e| log::warn!("Ignoring bad DataPackage cache for {checksum}: {e}"))
+        .ok()
+}
+
+/// Caches `data` under `checksum`, which should be the one from `RoomInfo` that we asked for
+pub fn save_cached(checksum: &str, data: &GameData) -> Result<()> {
+    let Some(path) = cache_file(checksum) else {
+        return Ok(());
+    };
+
+    fs::create_dir_all(AppPaths::get().data_package_dir())?;
+    fs::write(path, serde_json::to_string(data)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_file() {
+        let path = cache_file("0123456789abcdef").unwrap();
+        assert_eq!(path.file_name().unwrap(), "0123456789abcdef.json");
+        assert_eq!(path.parent().unwrap(), AppPaths::get().data_package_dir());
+
+        for checksum in ["", "../x", "..", "ABCDEF", "abc/def", "abc.json", " abc"] {
+            assert!(cache_file(checksum).is_none(), "{checksum:?} was accepted");
+            assert!(load_cached(checksum).is_none());
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use ap_rs::protocol::{HintData, PrintJSON};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use super::{
+    lib::{ItemID, LocationID, RegionID},
+    phase::BotPhase,
+};
+
+/// How many events a slow subscriber can fall behind before it starts missing them
+pub const EVENT_BUS_CAPACITY: usize = 256;
+
+/// Everything interesting that happens while the bot runs. Sent over `FullGameState::events`, for
+/// console output, saving, and any other integrations to subscribe to.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event")]
+pub enum BotEvent {
+    PhaseChanged {
+        from: BotPhase,
+        to: BotPhase,
+    },
+    ItemReceived {
+        item_id: ItemID,
+        /// The slot that found this item
+        sender: i32,
+        location: i32,
+        index: i32,
+    },
+    /// A full resync from the server moved our received item index on, without any new items
+    ItemIndexSynced {
+        index: i32,
+    },
+    KeyUnlocked {
+        region: RegionID,
+    },
+    LocationChecked {
+        location_id: LocationID,
+    },
+    /// A check never reached the server, so the chest is unchecked again to retry later
+    LocationUnchecked {
+        location_id: LocationID,
+    },
+    HintAdded {
+        hint: HintData,
+    },
+    /// A hinted location in our world was checked, or the hint was otherwise resolved
+    HintRemoved {
+        location: i32,
+    },
+    SpeedChanged {
+        from: f32,
+        to: f32,
+    },
+    /// The gameplay loop will make its next check in `wait_ms`. Sent again if the wait changes.
+    WaitStarted {
+        wait_ms: u64,
+    },
+    /// In co-pilot mode, the operator can pick which of these chests to check next. If nobody
+    /// does within `timeout_secs`, the bot picks one itself.
+    ChestChoiceOffered {
+        locations: Vec<LocationID>,
+        timeout_secs: u64,
+    },
+    BKStarted,
+    BKEnded,
+    IdleStarted {
+        until: Option<NaiveDateTime>,
+    },
+    IdleEnded,
+    CatchUpStarted {
+        missed_checks: u32,
+    },
+    CatchUpFinished {
+        caught_up: u32,
+        missed_checks: u32,
+    },
+    Goal,
+    Released,
+    Disconnected {
+        reason: String,
+    },
+    /// Anything the server wants shown to the player, like item sends, chat and joins
+    ServerMessage {
+        message: PrintJSON,
+    },
+    ConfigReloaded {
+        /// Some changes were left out, since they only apply on startup
+        needs_restart: bool,
+    },
+    /// The bot config changed on disk, but couldn't be read. The old one is still in use.
+    ConfigReloadFailed {
+        error: String,
+    },
+}
+
+impl BotEvent {
+    /// Whether this event comes with a change to what's in the save file
+    pub fn changes_save(&self) -> bool {
+        matches!(
+            self,
+            Self::ItemReceived { .. }
+                | Self::ItemIndexSynced { .. }
+                | Self::KeyUnlocked { .. }
+                | Self::LocationChecked { .. }
+                | Self::LocationUnchecked { .. }
+                | Self::HintAdded { .. }
+                | Self::HintRemoved { .. }
+                | Self::SpeedChanged { .. }
+                | Self::CatchUpFinished { .. }
+        )
+    }
+
+    /// Whether this event belongs in the journal. Server messages are left out, since they're
+    /// mostly other players' items and chat, and would drown out the run itself.
+    pub fn is_journaled(&self) -> bool {
+        !matches!(self, Self::ServerMessage { .. })
+    }
+}
@@ -1,25 +1,36 @@
 use std::{
     collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
-use anyhow::Result;
-use ap_rs::protocol::HintData;
+use anyhow::{bail, Result};
+use ap_rs::{
+    client::ArchipelagoClientSender,
+    protocol::{ClientStatus, HintData},
+};
+use chrono::{DateTime, Local, Utc};
 use rand::{seq::IteratorRandom, thread_rng};
 use serde::{Deserialize, Serialize};
-use tokio::{fs, sync::RwLock};
+use tokio::sync::{broadcast, Mutex, RwLock};
 
 use crate::utils::get_region_from_loc_id;
 
 use super::{
+    bot_config::{BotConfig, RoutingStrategy},
     chest::Chest,
-    lib::{ArchipelaPalSlotData, LocationID, RegionID, SAVE_FILE_DIRECTORY},
+    events::BotEvent,
+    lib::{ArchipelaPalSlotData, LocationID, RegionID},
+    names::NameResolver,
     offsets::CHEST_OFFSET,
+    paths::AppPaths,
+    phase::BotPhase,
     player::Player,
-    save_file::SaveFile,
+    save_file::{backup_path, SaveFile, CURRENT_SAVE_FORMAT_VERSION, SAVE_BACKUP_COUNT},
+    schedule::ActiveSchedule,
 };
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct FullGameState {
     pub player: Arc<RwLock<Player>>,
     pub map: Arc<RwLock<GameMap>>,
@@ -29,18 +40,104 @@ pub struct FullGameState {
     pub slot_id: i32,
     /// A queue of hints that we are currently searching for in OUR world
     pub source_hint_queue: Arc<RwLock<HashSet<HintData>>>,
+    /// The last time the gameplay loop ticked
+    pub last_active: Arc<RwLock<Option<DateTime<Utc>>>>,
+    /// How many goal items we need. Only `None` for saves from before this was stored.
+    pub num_goal: Option<u16>,
+    /// K = slot, V = alias, for the players on our team
+    pub player_names: HashMap<i32, String>,
+    /// For showing server messages with names instead of IDs. Not saved.
+    pub names: Arc<RwLock<NameResolver>>,
+    /// Set from the operator console, and applied on top of `Player::speed_modifier`. Not saved.
+    pub speed_override: Arc<RwLock<f32>>,
+    /// Client-side tuning, reloaded from disk while we run
+    pub bot_config: Arc<RwLock<BotConfig>>,
+    /// Event bus shared between the server task, the gameplay task, and any subscribers
+    pub events: broadcast::Sender<BotEvent>,
+    pub phase: Arc<RwLock<BotPhase>>,
+    /// Held while writing the save file
+    pub save_lock: Mutex<()>,
 }
 
 impl FullGameState {
+    /// Sends an event to all subscribers. It's fine if nobody is listening.
+    pub fn emit(&self, event: BotEvent) {
+        log::debug!("Event: {event:?}");
+        self.events.send(event).ok();
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<BotEvent> {
+        self.events.subscribe()
+    }
+
+    /// Our speed modifier, including any override from the operator
+    pub async fn effective_speed_modifier(&self) -> f32 {
+        self.player.read().await.speed_modifier * *self.speed_override.read().await
+    }
+
+    /// Moves the bot into `next`, and tells the server if our client status changed because of it
+    pub async fn set_phase(
+        &self,
+        sender: &mut ArchipelagoClientSender,
+        next: BotPhase,
+    ) -> Result<()> {
+        if let Some(status) = self.transition_to(next).await? {
+            sender.status_update(status).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the bot into `next`, without telling the server. Returns the client status the
+    /// server should be sent, if it changed.
+    pub async fn transition_to(&self, next: BotPhase) -> Result<Option<ClientStatus>> {
+        let mut phase = self.phase.write().await;
+        let prev = *phase;
+        if prev == next {
+            return Ok(None);
+        }
+
+        if !prev.can_transition_to(next) {
+            bail!("Invalid phase transition: {prev} -> {next}");
+        }
+
+        *phase = next;
+        drop(phase);
+
+        log::info!("Phase: {prev} -> {next}");
+        self.emit(BotEvent::PhaseChanged {
+            from: prev,
+            to: next,
+        });
+        match (prev, next) {
+            (_, BotPhase::BK) => self.emit(BotEvent::BKStarted),
+            (BotPhase::BK, BotPhase::Playing) => self.emit(BotEvent::BKEnded),
+            (_, BotPhase::Goaled) => self.emit(BotEvent::Goal),
+            (_, BotPhase::Released) => self.emit(BotEvent::Released),
+            _ => {}
+        }
+
+        if !next.changes_status_from(prev) {
+            return Ok(None);
+        }
+
+        Ok(next.client_status())
+    }
+
     /// Returns a checked location's ID, if we check one
     pub async fn tick_game_state(&self) -> Option<LocationID> {
+        *self.last_active.write().await = Some(Utc::now());
+
+        let routing = self.bot_config.read().await.routing.clone();
         let player = self.player.read().await;
         let player_region_keys = player.get_accessible_regions();
         log::debug!("Region keys: {:?}", player_region_keys);
 
         // Check if we can get something from the hint list first
         let source_hint_queue = self.source_hint_queue.read().await;
-        let hint_item = source_hint_queue.iter().find_map(|hint| {
+        // Empty when we aren't putting hints first
+        let hint_queue = routing.prioritize_hints.then_some(&*source_hint_queue);
+        let hint_item = hint_queue.into_iter().flatten().find_map(|hint| {
             if hint.item.player != self.slot_id {
                 log::warn!(
                     "Hint from another player in source hint queue! This is a bug! Ignoring."
@@ -77,61 +174,122 @@ impl FullGameState {
 
         let map = self.map.read().await;
         let search_region = player.currently_exploring_region;
-        let initial_chest = Self::choose_chest_in_region(&map, &search_region);
 
-        let alternate_chest = map.map.iter().find_map(|(region, chests)| {
-            if *region == search_region || !player_region_keys.contains(region) {
-                return None;
-            }
+        let mapped_chest_options = match routing.strategy {
+            RoutingStrategy::FinishRegion => {
+                let initial_chest = Self::choose_chest_in_region(&map, &search_region);
 
-            chests.iter().enumerate().find_map(|(idx, chest)| {
-                if !chest.checked {
-                    return Some((*region, idx));
-                }
+                let alternate_chest = reachable_chest_indices(&map, &player, &player_region_keys)
+                    .find(|(region, _)| *region != search_region);
 
-                None
-            })
-        });
+                initial_chest.map(|idx| (search_region, idx)).or_else(|| {
+                    log::debug!("No chest found in initial region, trying alternate...");
+                    alternate_chest
+                })
+            }
+            RoutingStrategy::Random => {
+                let mut rng = thread_rng();
+                reachable_chest_indices(&map, &player, &player_region_keys).choose(&mut rng)
+            }
+        };
+
+        let chosen_loc_id =
+            mapped_chest_options.map(|(region, idx)| map.map[&region][idx].full_id)?;
 
         drop(player);
         drop(map);
 
-        let mapped_chest_options = initial_chest.map(|idx| (search_region, idx)).or_else(|| {
-            log::debug!("No chest found in initial region, trying alternate...");
-            alternate_chest
-        });
+        self.check_location(chosen_loc_id)
+            .await
+            .then_some(chosen_loc_id)
+    }
 
-        let chosen_check = if let Some((chosen_region, chosen_chest_idx)) = mapped_chest_options {
-            if chosen_region != search_region {
-                let mut player = self.player.write().await;
-                player.currently_exploring_region = chosen_region;
-            }
+    /// Every unchecked chest we can get to right now
+    pub async fn reachable_chests(&self) -> Vec<LocationID> {
+        let player = self.player.read().await;
+        let map = self.map.read().await;
+        let keys = player.get_accessible_regions();
 
-            let mut map = self.map.write().await;
-            let chest = map
-                .map
-                .get_mut(&chosen_region)
-                .and_then(|region| region.get_mut(chosen_chest_idx));
+        let mut chests = reachable_chest_indices(&map, &player, &keys)
+            .map(|(region, idx)| map.map[&region][idx].full_id)
+            .collect::<Vec<_>>();
+        chests.sort();
 
-            if let Some(chest) = chest {
-                chest.checked = true;
+        chests
+    }
 
-                Some(chest.full_id)
-            } else {
-                None
+    /// Marks a chest as checked, and moves us to its region. Returns false if there's no such
+    /// chest, or it was already checked. Counts as being active, like a tick.
+    pub async fn check_location(&self, loc_id: LocationID) -> bool {
+        let region = get_region_from_loc_id(loc_id);
+
+        let mut map = self.map.write().await;
+        let Some(chest) = map
+            .map
+            .get_mut(&region)
+            .and_then(|chests| chests.iter_mut().find(|chest| chest.full_id == loc_id))
+        else {
+            return false;
+        };
+        if chest.checked {
+            return false;
+        }
+        chest.checked = true;
+        drop(map);
+
+        self.player.write().await.currently_exploring_region = region;
+        *self.last_active.write().await = Some(Utc::now());
+
+        true
+    }
+
+    /// Marks a chest as unchecked again, for when the check never made it to the server. Returns
+    /// false if there's no such chest, or it wasn't checked.
+    pub async fn uncheck_location(&self, loc_id: LocationID) -> bool {
+        let region = get_region_from_loc_id(loc_id);
+
+        let mut map = self.map.write().await;
+        match map
+            .map
+            .get_mut(&region)
+            .and_then(|chests| chests.iter_mut().find(|chest| chest.full_id == loc_id))
+        {
+            Some(chest) if chest.checked => {
+                chest.checked = false;
+                true
             }
-        } else {
-            None
+            _ => false,
+        }
+    }
+
+    /// Estimates how many checks we would have made since we were last active, at our current
+    /// speed. Only time inside `active_hours` counts. Returns 0 if we've never been active.
+    pub async fn missed_checks_since_last_active(
+        &self,
+        config: &ArchipelaPalSlotData,
+        active_hours: Option<&ActiveSchedule>,
+    ) -> u32 {
+        let Some(last_active) = *self.last_active.read().await else {
+            return 0;
         };
 
-        self.write_save_file()
-            .await
-            .inspect_err(|e| {
-                log::error!("Error saving file: {e}");
-            })
-            .ok();
+        let from = last_active.with_timezone(&Local).naive_local();
+        let to = Local::now().naive_local();
+        let downtime = active_hours
+            .cloned()
+            .unwrap_or_default()
+            .active_time_between(from, to);
+
+        let speed_modifier = self.player.read().await.speed_modifier;
+        let min_wait_time = config.min_wait_time as f32;
+        let average_wait_time = (min_wait_time + config.max_wait_time as f32) / 2.0;
+        let average_wait_time = (average_wait_time / speed_modifier).max(min_wait_time);
 
-        chosen_check
+        if average_wait_time <= 0.0 {
+            return 0;
+        }
+
+        (downtime.as_secs_f32() / average_wait_time) as u32
     }
 
     pub fn choose_chest_in_region(map_guard: &GameMap, region: &RegionID) -> Option<usize> {
@@ -155,8 +313,10 @@ impl FullGameState {
         let map_copy = self.map.read().await.clone();
         let last_checked_idx = *self.last_checked_idx.read().await;
         let source_hint_queue = self.source_hint_queue.read().await.clone();
+        let last_active = *self.last_active.read().await;
 
         let save_file = SaveFile {
+            format_version: CURRENT_SAVE_FORMAT_VERSION,
             player: player_copy,
             map: map_copy,
             seed: self.seed_name.clone(),
@@ -164,27 +324,94 @@ impl FullGameState {
             last_checked_idx,
             slot_id: self.slot_id,
             source_hint_queue,
+            last_active,
+            num_goal: self.num_goal,
+            player_names: self.player_names.clone(),
         };
 
-        let savefile_json = serde_json::to_string(&save_file)?;
-
-        let save_path = Self::make_save_file_name(&self.seed_name);
-        fs::write(save_path, savefile_json).await?;
+        // Only one write at a time, since they share a temp file
+        let _save_guard = self.save_lock.lock().await;
+        let save_path = Self::make_save_file_name(&self.seed_name, self.team, self.slot_id);
+        save_file.write_with_backups(&save_path).await?;
 
         Ok(())
     }
 
-    pub fn from_file_or_default(seed_name: &str) -> Self {
-        let name = Self::make_save_file_name(seed_name);
-        std::fs::read_to_string(&name)
-            .and_then(|file_str| serde_json::from_str::<SaveFile>(&file_str).map_err(|e| e.into()))
-            .inspect_err(|e| log::error!("Unable to read save file: {e}\nLoading a fresh save...."))
-            .unwrap_or_default()
-            .into()
+    /// Loads our save, or starts a fresh game if there isn't one. Errors if there's a save we
+    /// can't read, rather than start over on top of it.
+    pub fn from_file_or_default(seed_name: &str, team: i32, slot_id: i32) -> Result<Self> {
+        let name = Self::make_save_file_name(seed_name, team, slot_id);
+        if !name.exists() {
+            let legacy_name = AppPaths::get()
+                .saves_dir()
+                .join(format!("save-file-{seed_name}.json"));
+            Self::migrate_legacy_save_file(&legacy_name, team, slot_id, &name)
+                .inspect_err(|e| log::error!("Unable to migrate legacy save file: {e}"))
+                .ok();
+        }
+
+        let save = SaveFile::load_with_backups(&name)?;
+
+        Ok(save.unwrap_or_default().into())
+    }
+
+    pub fn make_journal_file_name(seed_name: &str, team: i32, slot_id: i32) -> PathBuf {
+        AppPaths::get()
+            .saves_dir()
+            .join(format!("journal-{seed_name}-{team}-{slot_id}.jsonl"))
     }
 
-    fn make_save_file_name(seed_name: &str) -> String {
-        format!("{SAVE_FILE_DIRECTORY}/save-file-{seed_name}.json")
+    pub fn make_save_file_name(seed_name: &str, team: i32, slot_id: i32) -> PathBuf {
+        AppPaths::get()
+            .saves_dir()
+            .join(format!("save-file-{seed_name}-{team}-{slot_id}.json"))
+    }
+
+    /// Saves used to be keyed by seed alone, which let two slots in the same seed clobber each
+    /// other. Moves a legacy save (and its backups) to `new_name`, if it belongs to this slot.
+    /// Another slot's legacy save is left exactly as it is, even if it's corrupt.
+    fn migrate_legacy_save_file(
+        legacy_name: &Path,
+        team: i32,
+        slot_id: i32,
+        new_name: &Path,
+    ) -> Result<()> {
+        let Some((legacy_team, legacy_slot_id)) = SaveFile::peek_owner(legacy_name) else {
+            return Ok(());
+        };
+        if legacy_slot_id != slot_id || legacy_team != team {
+            log::info!(
+                "Legacy save {} belongs to team {legacy_team} slot {legacy_slot_id}, not \
+                migrating it",
+                legacy_name.display()
+            );
+            return Ok(());
+        }
+
+        let Some(legacy_save) = SaveFile::load_with_backups(legacy_name)? else {
+            return Ok(());
+        };
+
+        if legacy_name.exists() {
+            std::fs::rename(legacy_name, new_name)?;
+        } else {
+            // The legacy save was corrupt, and got moved aside in favour of a backup
+            std::fs::write(new_name, serde_json::to_string(&legacy_save)?)?;
+        }
+        for n in 1..=SAVE_BACKUP_COUNT {
+            let legacy_backup = backup_path(legacy_name, n);
+            if legacy_backup.exists() {
+                std::fs::rename(legacy_backup, backup_path(new_name, n))?;
+            }
+        }
+
+        log::info!(
+            "Migrated legacy save {} to {}",
+            legacy_name.display(),
+            new_name.display()
+        );
+
+        Ok(())
     }
 
     pub fn make_hints_get_key(&self, slot_id: i32) -> String {
@@ -193,6 +420,27 @@ impl FullGameState {
     }
 }
 
+/// Every unchecked chest in a region we can get to, as (region, index in the region). That's the
+/// region we're exploring, and any we hold the key to.
+fn reachable_chest_indices<'a>(
+    map: &'a GameMap,
+    player: &'a Player,
+    keys: &'a [RegionID],
+) -> impl Iterator<Item = (RegionID, usize)> + 'a {
+    map.map
+        .iter()
+        .filter(|(region, _)| {
+            **region == player.currently_exploring_region || keys.contains(region)
+        })
+        .flat_map(|(region, chests)| {
+            chests
+                .iter()
+                .enumerate()
+                .filter(|(_, chest)| !chest.checked)
+                .map(|(idx, _)| (*region, idx))
+        })
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct GameMap {
     pub map: HashMap<RegionID, Vec<Chest>>,
@@ -219,4 +467,149 @@ impl GameMap {
 
         Self { map }
     }
+
+    /// Marks the given locations as checked, e.g. from the server's `checked_locations`.
+    /// Returns how many chests weren't already marked.
+    pub fn apply_checked_locations(&mut self, locations: &[i32]) -> usize {
+        let mut newly_checked = 0;
+        for &loc_id in locations {
+            let loc_id = loc_id as LocationID;
+            let region = get_region_from_loc_id(loc_id);
+            let chest = self
+                .map
+                .get_mut(&region)
+                .and_then(|chests| chests.iter_mut().find(|chest| chest.full_id == loc_id));
+
+            match chest {
+                Some(chest) if !chest.checked => {
+                    chest.checked = true;
+                    newly_checked += 1;
+                }
+                Some(_) => {}
+                None => {
+                    log::warn!("Server says location {loc_id} is checked, but it's not on our map")
+                }
+            }
+        }
+
+        newly_checked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    /// Regions 0 and 1, with two chests each
+    fn small_map() -> GameMap {
+        let mut map = GameMap::default();
+        for loc_id in [0x03000001, 0x03000002, 0x03010001, 0x03010002] {
+            let chest = Chest::new_from_id(loc_id);
+            map.map.entry(chest.region).or_default().push(chest);
+        }
+        map
+    }
+
+    fn is_checked(map: &GameMap, loc_id: LocationID) -> bool {
+        map.map
+            .values()
+            .flatten()
+            .any(|chest| chest.full_id == loc_id && chest.checked)
+    }
+
+    #[test]
+    fn test_apply_checked_locations() {
+        let mut map = small_map();
+        map.map.get_mut(&0).unwrap()[0].checked = true;
+
+        // One already checked, one new, and one that isn't ours
+        let newly_checked = map.apply_checked_locations(&[0x03000001, 0x03010002, 0x03090001]);
+
+        assert_eq!(newly_checked, 1);
+        assert!(is_checked(&map, 0x03000001));
+        assert!(is_checked(&map, 0x03010002));
+        assert!(!is_checked(&map, 0x03000002));
+        assert!(!is_checked(&map, 0x03010001));
+        assert_eq!(map.map.values().flatten().count(), 4);
+    }
+
+    #[test]
+    fn test_reachable_chests() {
+        let mut map = small_map();
+        let chest = Chest::new_from_id(0x03020001);
+        map.map.entry(chest.region).or_default().push(chest);
+        map.map.get_mut(&0).unwrap()[0].checked = true;
+
+        // In the hub, with the key to region 2 but not region 1
+        let player = Player {
+            inventory: HashMap::from([(0x00020002, 1)]),
+            ..Default::default()
+        };
+        let keys = player.get_accessible_regions();
+        let mut reachable = reachable_chest_indices(&map, &player, &keys)
+            .map(|(region, idx)| map.map[&region][idx].full_id)
+            .collect::<Vec<_>>();
+        reachable.sort();
+
+        assert_eq!(reachable, vec![0x03000002, 0x03020001]);
+    }
+
+    fn legacy_save_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("archipelapal-{name}-{}", process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_migrate_own_legacy_save() {
+        let dir = legacy_save_dir("legacy-own");
+        let legacy_name = dir.join("save-file-1234.json");
+        let new_name = dir.join("save-file-1234-0-2.json");
+        let save = SaveFile {
+            slot_id: 2,
+            last_checked_idx: 7,
+            ..Default::default()
+        };
+        save.write_with_backups(&legacy_name).await.unwrap();
+        save.write_with_backups(&legacy_name).await.unwrap();
+
+        FullGameState::migrate_legacy_save_file(&legacy_name, 0, 2, &new_name).unwrap();
+
+        assert!(!legacy_name.exists());
+        assert!(!backup_path(&legacy_name, 1).exists());
+        assert!(backup_path(&new_name, 1).exists());
+        let migrated = SaveFile::read_from(&new_name).unwrap();
+        assert_eq!(migrated.last_checked_idx, 7);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_leave_other_slots_legacy_save() {
+        let dir = legacy_save_dir("legacy-other");
+        let legacy_name = dir.join("save-file-1234.json");
+        let new_name = dir.join("save-file-1234-0-2.json");
+        // Another slot's save, with the primary corrupt and only a backup to go on
+        std::fs::write(&legacy_name, "{\"player\": {").unwrap();
+        std::fs::write(
+            backup_path(&legacy_name, 1),
+            serde_json::to_string(&SaveFile {
+                slot_id: 3,
+                ..Default::default()
+            })
+            .unwrap(),
+        )
+        .unwrap();
+
+        FullGameState::migrate_legacy_save_file(&legacy_name, 0, 2, &new_name).unwrap();
+
+        assert!(legacy_name.exists());
+        assert!(backup_path(&legacy_name, 1).exists());
+        assert!(!legacy_name.with_extension("json.corrupt").exists());
+        assert!(!new_name.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
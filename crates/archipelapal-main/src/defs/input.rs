@@ -0,0 +1,67 @@
+use std::{
+    io::{stdin, BufRead},
+    sync::OnceLock,
+    thread,
+    time::Duration,
+};
+
+use tokio::sync::{
+    mpsc::{self, error::TryRecvError, UnboundedReceiver},
+    Mutex,
+};
+
+/// How often `read_line` checks for a line, once stdin is read on its own thread
+const READ_LINE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Lines from stdin, read on a dedicated thread. Tokio's own `stdin` reads on a blocking thread it
+/// can't cancel, which holds up shutdown until Enter is pressed. A plain thread doesn't, and
+/// sharing its lines means prompts after the operator console still get what's typed.
+static LINES: OnceLock<Mutex<UnboundedReceiver<String>>> = OnceLock::new();
+
+fn lines() -> &'static Mutex<UnboundedReceiver<String>> {
+    LINES.get_or_init(|| {
+        let (tx, rx) = mpsc::unbounded_channel();
+        thread::spawn(move || {
+            for line in stdin().lock().lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(e) => {
+                        log::error!("Could not read from stdin: {e}");
+                        continue;
+                    }
+                };
+                if tx.send(line).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Mutex::new(rx)
+    })
+}
+
+/// The next line typed, or `None` once stdin is closed
+pub async fn next_line() -> Option<String> {
+    lines().lock().await.recv().await
+}
+
+/// Blocks until the next line is typed. Reads stdin directly unless the stdin thread is already
+/// running, in which case the line comes from there. Empty once stdin is closed.
+pub fn read_line() -> std::io::Result<String> {
+    let Some(lines) = LINES.get() else {
+        let mut buf = String::new();
+        stdin().read_line(&mut buf)?;
+        return Ok(buf);
+    };
+
+    loop {
+        if let Ok(mut lines) = lines.try_lock() {
+            match lines.try_recv() {
+                Ok(line) => return Ok(line),
+                Err(TryRecvError::Disconnected) => return Ok(String::new()),
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+        thread::sleep(READ_LINE_POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,304 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock, RwLock,
+    },
+};
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use clap::ValueEnum;
+use log::{Level, Log, Metadata, Record};
+use serde_json::{json, Value};
+
+use super::events::BotEvent;
+
+pub const LOG_FILE: &str = "archipelapal.log";
+
+/// Bot events are logged at the info level under this target, so `RUST_LOG` can pick them out
+const EVENT_TARGET: &str = "ArchipelaPal::event";
+
+/// Rotate the log file once it gets this big
+const MAX_LOG_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// How many rotated log files to keep, as `archipelapal.log.1` (newest) and up
+const LOG_FILE_BACKUP_COUNT: usize = 3;
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Set while something else owns the terminal, like the dashboard. Lines still go to the file.
+static STDERR_MUTED: AtomicBool = AtomicBool::new(false);
+
+/// Which bot this process is, for telling log lines apart when collecting them from several bots
+static CONTEXT: RwLock<LogContext> = RwLock::new(LogContext {
+    slot: None,
+    seed: None,
+});
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Plain lines, like `[12:00:00 INFO  target] message`
+    #[default]
+    Text,
+    /// One JSON object per line, including one for every bot event
+    Json,
+}
+
+#[derive(Clone, Debug)]
+struct LogContext {
+    slot: Option<String>,
+    seed: Option<String>,
+}
+
+/// Logs to stderr and a size-rotated file in `log_dir`, filtered by `RUST_LOG` as before
+struct Logger {
+    filter: env_logger::Logger,
+    format: LogFormat,
+    file: Mutex<Option<RotatingFile>>,
+}
+
+pub fn init(format: LogFormat, log_dir: &Path) -> Result<()> {
+    let filter = env_logger::Builder::from_default_env().build();
+    let max_level = filter.filter();
+
+    let file = RotatingFile::open(log_dir.join(LOG_FILE))
+        .inspect_err(|e| eprintln!("Could not open the log file, only logging to stderr: {e}"))
+        .ok();
+
+    let logger = LOGGER.get_or_init(|| Logger {
+        filter,
+        format,
+        file: Mutex::new(file),
+    });
+    log::set_logger(logger).context("Logger was already initialized")?;
+    log::set_max_level(max_level);
+
+    Ok(())
+}
+
+/// Stops (or restarts) logging to stderr, for while the terminal is showing something else
+pub fn mute_stderr(muted: bool) {
+    STDERR_MUTED.store(muted, Ordering::Relaxed);
+}
+
+/// Tags every following log line with our slot and seed, and moves logging over to this bot's
+/// own file, so bots running side by side don't write to (and rotate) the same one
+pub fn set_context(slot: &str, seed: &str) {
+    {
+        let mut context = CONTEXT.write().unwrap_or_else(|e| e.into_inner());
+        context.slot = Some(slot.to_string());
+        context.seed = Some(seed.to_string());
+    }
+
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    let mut file = logger.file.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(dir) = file
+        .as_ref()
+        .and_then(|f| f.path.parent().map(Path::to_path_buf))
+    else {
+        return;
+    };
+    match RotatingFile::open(dir.join(bot_log_file(slot, seed))) {
+        Ok(bot_file) => *file = Some(bot_file),
+        Err(e) => {
+            drop(file);
+            log::warn!("Could not open this bot's log file, staying on {LOG_FILE}: {e}");
+        }
+    }
+}
+
+/// `archipelapal-<seed>-<slot>.log`, with anything that can't go in a file name replaced
+fn bot_log_file(slot: &str, seed: &str) -> String {
+    let sanitize = |s: &str| -> String {
+        s.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    };
+
+    format!("archipelapal-{}-{}.log", sanitize(seed), sanitize(slot))
+}
+
+/// Writes `event` as a JSON log line, with all of its fields, if `RUST_LOG` lets info logs
+/// through. Does nothing for text logs, where events are already described by the console output.
+pub fn log_event(event: &BotEvent) {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    let metadata = Metadata::builder()
+        .level(Level::Info)
+        .target(EVENT_TARGET)
+        .build();
+    if logger.format != LogFormat::Json || !logger.enabled(&metadata) {
+        return;
+    }
+
+    let context = CONTEXT.read().unwrap_or_else(|e| e.into_inner()).clone();
+    logger.write_line(&event_record(event, &context).to_string());
+}
+
+fn event_record(event: &BotEvent, context: &LogContext) -> Value {
+    let mut record = json!({
+        "ts": Local::now().to_rfc3339(),
+        "level": "INFO",
+        "target": EVENT_TARGET,
+        "slot": context.slot,
+        "seed": context.seed,
+    });
+    if let (Some(record), Ok(Value::Object(fields))) =
+        (record.as_object_mut(), serde_json::to_value(event))
+    {
+        record.extend(fields);
+    }
+
+    record
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.filter.matches(record) {
+            return;
+        }
+
+        let line = match self.format {
+            LogFormat::Text => format!(
+                "[{} {:<5} {}] {}",
+                Local::now().format("%Y-%m-%dT%H:%M:%S"),
+                record.level(),
+                record.target(),
+                record.args()
+            ),
+            LogFormat::Json => {
+                let context = CONTEXT.read().unwrap_or_else(|e| e.into_inner()).clone();
+                json!({
+                    "ts": Local::now().to_rfc3339(),
+                    "level": record.level().as_str(),
+                    "target": record.target(),
+                    "slot": context.slot,
+                    "seed": context.seed,
+                    "message": record.args().to_string(),
+                })
+                .to_string()
+            }
+        };
+
+        self.write_line(&line);
+    }
+
+    fn flush(&self) {
+        if let Some(file) = self.file.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            file.file.flush().ok();
+        }
+    }
+}
+
+impl Logger {
+    fn write_line(&self, line: &str) {
+        if !STDERR_MUTED.load(Ordering::Relaxed) {
+            eprintln!("{line}");
+        }
+
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(file) = file.as_mut() {
+            // Nowhere left to report a failure to log
+            file.write_line(line).ok();
+        }
+    }
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self { path, file, size })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 + 1 > MAX_LOG_FILE_BYTES {
+            self.rotate()?;
+        }
+
+        writeln!(self.file, "{line}")?;
+        self.size += line.len() as u64 + 1;
+
+        Ok(())
+    }
+
+    /// `archipelapal.log` becomes `archipelapal.log.1`, `.1` becomes `.2`, and so on, dropping
+    /// the oldest
+    fn rotate(&mut self) -> io::Result<()> {
+        for n in (1..LOG_FILE_BACKUP_COUNT).rev() {
+            let from = backup_path(&self.path, n);
+            if from.exists() {
+                fs::rename(&from, backup_path(&self.path, n + 1))?;
+            }
+        }
+        fs::rename(&self.path, backup_path(&self.path, 1))?;
+
+        *self = Self::open(self.path.clone())?;
+
+        Ok(())
+    }
+}
+
+fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".{n}"));
+    path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_record() {
+        let context = LogContext {
+            slot: Some("Pal".to_string()),
+            seed: Some("1234".to_string()),
+        };
+        let record = event_record(
+            &BotEvent::LocationChecked {
+                location_id: 0x03010101,
+            },
+            &context,
+        );
+
+        assert_eq!(record["event"], "LocationChecked");
+        assert_eq!(record["location_id"], 0x03010101);
+        assert_eq!(record["slot"], "Pal");
+        assert_eq!(record["seed"], "1234");
+    }
+
+    #[test]
+    fn test_bot_log_file() {
+        assert_eq!(
+            bot_log_file("Pal 2/../x", "1234"),
+            "archipelapal-1234-Pal_2____x.log"
+        );
+    }
+}
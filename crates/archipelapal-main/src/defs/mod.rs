@@ -1,8 +1,22 @@
+pub mod bot_config;
 pub mod chest;
+pub mod control;
+pub mod data_package;
+pub mod events;
 pub mod game_state;
+pub mod input;
 pub mod items;
 pub mod lib;
+pub mod logging;
+pub mod names;
 pub mod offsets;
+pub mod paths;
+pub mod phase;
 pub mod player;
+pub mod print_json;
 pub mod save_file;
+pub mod save_lock;
+pub mod schedule;
+pub mod secret;
+pub mod theme;
 pub mod user_settings;
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use super::{
+    data_package::GameNames,
+    items::Item,
+    lib::{ItemID, RegionID},
+    theme::{Theme, PLAIN},
+};
+
+/// Turns the slot, item and location IDs the server sends us into names, the way the official text
+/// client does. Anything we don't know a name for is shown by its ID.
+#[derive(Clone, Debug)]
+pub struct NameResolver {
+    pub own_slot: i32,
+    /// Slot to alias, for everyone on our team
+    pub players: HashMap<i32, String>,
+    /// Slot to the game it plays
+    pub slot_games: HashMap<i32, String>,
+    /// Filled in from the DataPackage, as it arrives
+    pub games: HashMap<String, GameNames>,
+    /// How to word our own regions and checks
+    pub theme: &'static Theme,
+}
+
+impl Default for NameResolver {
+    fn default() -> Self {
+        Self::new(0, HashMap::new(), HashMap::new())
+    }
+}
+
+impl NameResolver {
+    pub fn new(
+        own_slot: i32,
+        players: HashMap<i32, String>,
+        slot_games: HashMap<i32, String>,
+    ) -> Self {
+        Self {
+            own_slot,
+            players,
+            slot_games,
+            games: HashMap::new(),
+            theme: &PLAIN,
+        }
+    }
+
+    pub fn add_game(&mut self, game: String, names: GameNames) {
+        self.games.insert(game, names);
+    }
+
+    pub fn player(&self, slot: i32) -> String {
+        match self.players.get(&slot) {
+            Some(name) => name.clone(),
+            None if slot == 0 => "Server".to_string(),
+            None => format!("Player {slot}"),
+        }
+    }
+
+    /// `player` is the slot that receives the item
+    pub fn item(&self, item_id: i32, player: i32) -> String {
+        self.game_names(player)
+            .and_then(|names| names.items.get(&item_id))
+            .cloned()
+            .unwrap_or_else(|| format!("Item {item_id}"))
+    }
+
+    /// `player` is the slot whose world the location is in
+    pub fn location(&self, location_id: i32, player: i32) -> String {
+        self.try_location(location_id, player)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("Location {location_id}"))
+    }
+
+    pub fn try_location(&self, location_id: i32, player: i32) -> Option<&str> {
+        self.game_names(player)
+            .and_then(|names| names.locations.get(&location_id))
+            .map(String::as_str)
+    }
+
+    /// What our own world calls the key to `region`, for hinting. `None` until our game's
+    /// DataPackage has arrived.
+    pub fn key_item(&self, region: RegionID) -> Option<String> {
+        self.game_names(self.own_slot)?
+            .items
+            .iter()
+            .find(|(id, _)| matches!(Item::from_id(**id as ItemID), Some(Item::Key(r)) if r == region))
+            .map(|(_, name)| name.clone())
+    }
+
+    fn game_names(&self, slot: i32) -> Option<&GameNames> {
+        self.slot_games
+            .get(&slot)
+            .and_then(|game| self.games.get(game))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_item() {
+        let mut names = NameResolver::new(
+            1,
+            HashMap::new(),
+            HashMap::from([(1, "ArchipelaPal".to_string())]),
+        );
+        assert_eq!(names.key_item(3), None);
+
+        names.add_game(
+            "ArchipelaPal".to_string(),
+            GameNames {
+                items: HashMap::from([
+                    (0x00010000, "Goal".to_string()),
+                    (0x00020002, "Region 2 Key".to_string()),
+                    (0x00020003, "Region 3 Key".to_string()),
+                ]),
+                locations: HashMap::new(),
+            },
+        );
+        assert_eq!(names.key_item(3).as_deref(), Some("Region 3 Key"));
+        assert_eq!(names.key_item(4), None);
+    }
+}
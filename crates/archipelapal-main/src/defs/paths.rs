@@ -0,0 +1,279 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use anyhow::{Context, Result};
+
+use super::{
+    bot_config::BOT_CONFIG_FILE, lib::SAVE_FILE_DIRECTORY, user_settings::USER_SETTINGS_FILE,
+};
+
+const APP_DIR_NAME: &str = "archipelapal";
+const DATA_PACKAGE_DIRECTORY: &str = "DataPackages";
+const LOG_DIRECTORY: &str = "Logs";
+
+/// Left in the data directory once we've looked for files from older versions, so we only do it
+/// once
+const MIGRATION_MARKER: &str = ".migrated-from-cwd";
+
+static APP_PATHS: OnceLock<AppPaths> = OnceLock::new();
+
+/// Where we keep our data (saves) and config (user settings)
+#[derive(Debug, Clone)]
+pub struct AppPaths {
+    pub data_dir: PathBuf,
+    pub config_dir: PathBuf,
+}
+
+impl AppPaths {
+    /// Uses the given directories, falling back to the platform defaults
+    pub fn resolve(data_dir: Option<PathBuf>, config_dir: Option<PathBuf>) -> Self {
+        Self {
+            data_dir: data_dir.unwrap_or_else(default_data_dir),
+            config_dir: config_dir.unwrap_or_else(default_config_dir),
+        }
+    }
+
+    /// Sets the paths used for the rest of the program. Must be called before anything reads
+    /// `AppPaths::get`.
+    pub fn init(self) -> &'static AppPaths {
+        if APP_PATHS.set(self).is_err() {
+            log::warn!("App paths were already initialized, ignoring");
+        }
+
+        Self::get()
+    }
+
+    pub fn get() -> &'static AppPaths {
+        APP_PATHS.get_or_init(|| Self::resolve(None, None))
+    }
+
+    pub fn saves_dir(&self) -> PathBuf {
+        self.data_dir.join(SAVE_FILE_DIRECTORY)
+    }
+
+    /// Cached item and location names, see `data_package`
+    pub fn data_package_dir(&self) -> PathBuf {
+        self.data_dir.join(DATA_PACKAGE_DIRECTORY)
+    }
+
+    pub fn log_dir(&self) -> PathBuf {
+        self.data_dir.join(LOG_DIRECTORY)
+    }
+
+    pub fn user_settings_file(&self) -> PathBuf {
+        self.config_dir.join(USER_SETTINGS_FILE)
+    }
+
+    pub fn bot_config_file(&self) -> PathBuf {
+        self.config_dir.join(BOT_CONFIG_FILE)
+    }
+
+    pub fn create_dirs(&self) -> Result<()> {
+        fs::create_dir_all(self.saves_dir()).with_context(|| {
+            format!(
+                "Could not create saves directory {}",
+                self.saves_dir().display()
+            )
+        })?;
+        fs::create_dir_all(&self.config_dir).with_context(|| {
+            format!(
+                "Could not create config directory {}",
+                self.config_dir.display()
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// Older versions kept everything in the current directory. The first time we play, moves any
+    /// saves, journals and user settings found there into our directories, without overwriting
+    /// anything.
+    pub fn migrate_from_cwd(&self) -> Result<()> {
+        let marker = self.data_dir.join(MIGRATION_MARKER);
+        if marker.exists() {
+            return Ok(());
+        }
+
+        self.migrate_from(&env::current_dir()?)?;
+        fs::write(&marker, "").with_context(|| format!("Could not write {}", marker.display()))?;
+
+        Ok(())
+    }
+
+    fn migrate_from(&self, old_dir: &Path) -> Result<()> {
+        let old_saves_dir = old_dir.join(SAVE_FILE_DIRECTORY);
+        if old_saves_dir.is_dir() && !is_same_path(&old_saves_dir, &self.saves_dir()) {
+            for entry in fs::read_dir(&old_saves_dir)? {
+                let entry = entry?;
+                if !is_legacy_save_data(&entry.file_name().to_string_lossy()) {
+                    continue;
+                }
+
+                let new_path = self.saves_dir().join(entry.file_name());
+                if new_path.exists() {
+                    log::warn!(
+                        "Not migrating {}, {} already exists",
+                        entry.path().display(),
+                        new_path.display()
+                    );
+                    continue;
+                }
+
+                move_file(&entry.path(), &new_path)?;
+                log::info!(
+                    "Migrated {} to {}",
+                    entry.path().display(),
+                    new_path.display()
+                );
+            }
+
+            // Only removes the directory if we moved everything out of it
+            fs::remove_dir(&old_saves_dir).ok();
+        }
+
+        let old_settings_file = old_dir.join(USER_SETTINGS_FILE);
+        let new_settings_file = self.user_settings_file();
+        if old_settings_file.is_file()
+            && !is_same_path(&old_settings_file, &new_settings_file)
+            && !new_settings_file.exists()
+        {
+            move_file(&old_settings_file, &new_settings_file)?;
+            log::info!(
+                "Migrated {} to {}",
+                old_settings_file.display(),
+                new_settings_file.display()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// `$XDG_DATA_HOME/archipelapal` on Linux. Elsewhere, the current directory, like always.
+fn default_data_dir() -> PathBuf {
+    if cfg!(target_os = "linux") {
+        if let Some(dir) = xdg_dir("XDG_DATA_HOME", ".local/share") {
+            return dir;
+        }
+    }
+
+    PathBuf::from(".")
+}
+
+/// `$XDG_CONFIG_HOME/archipelapal` on Linux. Elsewhere, the current directory, like always.
+fn default_config_dir() -> PathBuf {
+    if cfg!(target_os = "linux") {
+        if let Some(dir) = xdg_dir("XDG_CONFIG_HOME", ".config") {
+            return dir;
+        }
+    }
+
+    PathBuf::from(".")
+}
+
+fn xdg_dir(var: &str, home_fallback: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .map(PathBuf::from)
+        // The spec says relative paths should be ignored
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(home_fallback)))
+        .map(|dir| dir.join(APP_DIR_NAME))
+}
+
+/// Save files (`save-file-*.json`), their `.bakN` backups, and journals (`journal-*.jsonl`). Not
+/// lock files, which belong to whichever instance is running, or anything else that happens to be
+/// in a `Saves` directory.
+fn is_legacy_save_data(file_name: &str) -> bool {
+    if let Some(rest) = file_name.strip_prefix("save-file-") {
+        let is_backup = rest
+            .rsplit_once(".json.bak")
+            .is_some_and(|(_, n)| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+        return rest.ends_with(".json") || is_backup;
+    }
+
+    file_name.starts_with("journal-") && file_name.ends_with(".jsonl")
+}
+
+fn is_same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Renames `from` to `to`, falling back to copying when they're on different filesystems
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let paths = AppPaths::resolve(Some("/data".into()), Some("/config".into()));
+
+        assert_eq!(
+            paths.saves_dir(),
+            Path::new("/data").join(SAVE_FILE_DIRECTORY)
+        );
+        assert_eq!(paths.log_dir(), Path::new("/data/Logs"));
+        assert_eq!(
+            paths.user_settings_file(),
+            Path::new("/config").join(USER_SETTINGS_FILE)
+        );
+        assert_eq!(
+            paths.bot_config_file(),
+            Path::new("/config").join(BOT_CONFIG_FILE)
+        );
+    }
+
+    #[test]
+    fn test_migrate_from() {
+        let root = env::temp_dir().join(format!("archipelapal-paths-{}", process::id()));
+        let old_dir = root.join("old");
+        let old_saves_dir = old_dir.join(SAVE_FILE_DIRECTORY);
+        let paths = AppPaths::resolve(Some(root.join("data")), Some(root.join("config")));
+        fs::create_dir_all(&old_saves_dir).unwrap();
+        paths.create_dirs().unwrap();
+
+        let migrated = [
+            "save-file-1234.json",
+            "save-file-1234-0-1.json",
+            "save-file-1234-0-1.json.bak2",
+            "journal-1234-0-1.jsonl",
+        ];
+        let left_alone = [
+            "save-file-1234-0-1.json.lock",
+            "save-file-1234-0-1.json.corrupt",
+            "notes.txt",
+        ];
+        for name in migrated.iter().chain(&left_alone) {
+            fs::write(old_saves_dir.join(name), name).unwrap();
+        }
+        fs::write(old_dir.join(USER_SETTINGS_FILE), "{}").unwrap();
+
+        paths.migrate_from(&old_dir).unwrap();
+
+        for name in migrated {
+            assert!(paths.saves_dir().join(name).exists(), "{name} wasn't moved");
+        }
+        for name in left_alone {
+            assert!(old_saves_dir.join(name).exists(), "{name} was moved");
+        }
+        assert!(paths.user_settings_file().exists());
+
+        fs::remove_dir_all(&root).ok();
+    }
+}
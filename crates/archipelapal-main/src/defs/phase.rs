@@ -0,0 +1,127 @@
+use std::fmt;
+
+use ap_rs::protocol::ClientStatus;
+use serde::Serialize;
+
+/// The lifecycle of the bot, from connecting to the server to goaling and releasing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub enum BotPhase {
+    #[default]
+    Connecting,
+    /// Connected and syncing with the server
+    Connected,
+    /// Waiting for the user to start the game
+    AwaitingStart,
+    Playing,
+    BK,
+    /// Paused by the operator
+    Paused,
+    Goaled,
+    Released,
+    Disconnected,
+}
+
+impl BotPhase {
+    pub fn can_transition_to(self, next: BotPhase) -> bool {
+        use BotPhase::*;
+
+        if self == next {
+            return true;
+        }
+
+        match self {
+            Connecting => matches!(next, Connected | Disconnected),
+            Connected => matches!(next, AwaitingStart | Playing | Goaled | Disconnected),
+            AwaitingStart => matches!(next, Playing | Goaled | Disconnected),
+            Playing => matches!(next, BK | Paused | Goaled | Disconnected),
+            BK => matches!(next, Playing | Paused | Goaled | Disconnected),
+            Paused => matches!(next, Playing | Goaled | Disconnected),
+            Goaled => matches!(next, Released | Disconnected),
+            Released => matches!(next, Disconnected),
+            Disconnected => matches!(next, Connecting),
+        }
+    }
+
+    /// The status the server should see for us while we're in this phase
+    pub fn client_status(self) -> Option<ClientStatus> {
+        match self {
+            BotPhase::Connected => Some(ClientStatus::ClientConnected),
+            BotPhase::AwaitingStart => Some(ClientStatus::ClientReady),
+            BotPhase::Playing | BotPhase::BK | BotPhase::Paused => {
+                Some(ClientStatus::ClientPlaying)
+            }
+            BotPhase::Goaled | BotPhase::Released => Some(ClientStatus::ClientGoal),
+            BotPhase::Connecting | BotPhase::Disconnected => None,
+        }
+    }
+
+    /// Whether moving from `prev` to this phase changes what the server should see
+    pub fn changes_status_from(self, prev: BotPhase) -> bool {
+        let status = |phase: BotPhase| phase.client_status().map(|status| status as u16);
+        status(self) != status(prev)
+    }
+}
+
+impl fmt::Display for BotPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BotPhase::Connecting => "Connecting",
+            BotPhase::Connected => "Connected",
+            BotPhase::AwaitingStart => "Waiting to start",
+            BotPhase::Playing => "Playing",
+            BotPhase::BK => "BK",
+            BotPhase::Paused => "Paused",
+            BotPhase::Goaled => "Goaled",
+            BotPhase::Released => "Released",
+            BotPhase::Disconnected => "Disconnected",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transitions() {
+        assert!(BotPhase::Connecting.can_transition_to(BotPhase::Connected));
+        assert!(BotPhase::Playing.can_transition_to(BotPhase::BK));
+        assert!(BotPhase::BK.can_transition_to(BotPhase::Playing));
+        assert!(BotPhase::Goaled.can_transition_to(BotPhase::Released));
+
+        assert!(!BotPhase::Connecting.can_transition_to(BotPhase::Playing));
+        assert!(!BotPhase::Goaled.can_transition_to(BotPhase::Playing));
+        assert!(!BotPhase::Released.can_transition_to(BotPhase::BK));
+    }
+
+    #[test]
+    fn test_client_status() {
+        assert!(matches!(
+            BotPhase::Connected.client_status(),
+            Some(ClientStatus::ClientConnected)
+        ));
+        assert!(matches!(
+            BotPhase::AwaitingStart.client_status(),
+            Some(ClientStatus::ClientReady)
+        ));
+        for phase in [BotPhase::Playing, BotPhase::BK, BotPhase::Paused] {
+            assert!(matches!(
+                phase.client_status(),
+                Some(ClientStatus::ClientPlaying)
+            ));
+        }
+        for phase in [BotPhase::Goaled, BotPhase::Released] {
+            assert!(matches!(
+                phase.client_status(),
+                Some(ClientStatus::ClientGoal)
+            ));
+        }
+        assert!(BotPhase::Connecting.client_status().is_none());
+        assert!(BotPhase::Disconnected.client_status().is_none());
+
+        assert!(BotPhase::Playing.changes_status_from(BotPhase::AwaitingStart));
+        assert!(!BotPhase::BK.changes_status_from(BotPhase::Playing));
+        assert!(!BotPhase::Released.changes_status_from(BotPhase::Goaled));
+    }
+}
@@ -0,0 +1,156 @@
+use ap_rs::protocol::{JSONMessagePart, PrintJSON};
+use ratatui::crossterm::style::{Attribute, Color, Stylize};
+
+use super::names::NameResolver;
+
+const FLAG_PROGRESSION: i32 = 0b001;
+const FLAG_USEFUL: i32 = 0b010;
+const FLAG_TRAP: i32 = 0b100;
+
+/// Renders a `PrintJSON` message (item sends, hints, chat, joins, countdowns, ...) as one line of
+/// text, with names filled in. Colors follow the official text client, and are left out when
+/// `colored` is false.
+pub fn render_print_json(message: &PrintJSON, names: &NameResolver, colored: bool) -> String {
+    message
+        .data
+        .iter()
+        .map(|part| render_part(part, names, colored))
+        .collect()
+}
+
+fn render_part(part: &JSONMessagePart, names: &NameResolver, colored: bool) -> String {
+    let (text, style) = match part {
+        JSONMessagePart::PlayerId { text } => match text.parse::<i32>() {
+            Ok(slot) => (names.player(slot), player_style(names, slot)),
+            Err(_) => (text.clone(), Style::Color(Color::Yellow)),
+        },
+        JSONMessagePart::PlayerName { text } => (text.clone(), Style::Color(Color::Yellow)),
+        JSONMessagePart::ItemId {
+            text,
+            flags,
+            player,
+        } => {
+            let name = match text.parse::<i32>() {
+                Ok(item_id) => names.item(item_id, *player),
+                Err(_) => text.clone(),
+            };
+            (name, item_style(*flags))
+        }
+        JSONMessagePart::ItemName { text, flags, .. } => (text.clone(), item_style(*flags)),
+        JSONMessagePart::LocationId { text, player } => {
+            let name = match text.parse::<i32>() {
+                Ok(location_id) => names.location(location_id, *player),
+                Err(_) => text.clone(),
+            };
+            (name, Style::Color(Color::Green))
+        }
+        JSONMessagePart::LocationName { text, .. } => (text.clone(), Style::Color(Color::Green)),
+        JSONMessagePart::EntranceName { text } => (text.clone(), Style::Color(Color::Blue)),
+        JSONMessagePart::Color { text, color } => {
+            // Whatever the color type is, it serializes to the name the server sent
+            let color_name = serde_json::to_value(color).unwrap_or_default();
+            (text.clone(), named_style(color_name.as_str().unwrap_or("")))
+        }
+        JSONMessagePart::Text { text } => (text.clone(), Style::Plain),
+    };
+
+    match colored {
+        true => style.paint(text),
+        false => text,
+    }
+}
+
+enum Style {
+    Plain,
+    Color(Color),
+    Background(Color),
+    Attribute(Attribute),
+}
+
+impl Style {
+    fn paint(self, text: String) -> String {
+        match self {
+            Style::Plain => text,
+            Style::Color(color) => text.with(color).to_string(),
+            Style::Background(color) => text.on(color).to_string(),
+            Style::Attribute(attribute) => text.attribute(attribute).to_string(),
+        }
+    }
+}
+
+fn player_style(names: &NameResolver, slot: i32) -> Style {
+    match slot == names.own_slot {
+        true => Style::Color(Color::Magenta),
+        false => Style::Color(Color::Yellow),
+    }
+}
+
+fn item_style(flags: i32) -> Style {
+    if flags & FLAG_PROGRESSION != 0 {
+        Style::Color(Color::Magenta)
+    } else if flags & FLAG_USEFUL != 0 {
+        Style::Color(Color::Blue)
+    } else if flags & FLAG_TRAP != 0 {
+        Style::Color(Color::Red)
+    } else {
+        Style::Color(Color::Cyan)
+    }
+}
+
+/// The colors a `Color` part can ask for, from the network protocol
+fn named_style(name: &str) -> Style {
+    match name {
+        "bold" => Style::Attribute(Attribute::Bold),
+        "underline" => Style::Attribute(Attribute::Underlined),
+        "black" => Style::Color(Color::Black),
+        "red" => Style::Color(Color::Red),
+        "green" => Style::Color(Color::Green),
+        "yellow" => Style::Color(Color::Yellow),
+        "blue" => Style::Color(Color::Blue),
+        "magenta" => Style::Color(Color::Magenta),
+        "cyan" => Style::Color(Color::Cyan),
+        "white" => Style::Color(Color::White),
+        "black_bg" => Style::Background(Color::Black),
+        "red_bg" => Style::Background(Color::Red),
+        "green_bg" => Style::Background(Color::Green),
+        "yellow_bg" => Style::Background(Color::Yellow),
+        "blue_bg" => Style::Background(Color::Blue),
+        "magenta_bg" => Style::Background(Color::Magenta),
+        "cyan_bg" => Style::Background(Color::Cyan),
+        "white_bg" => Style::Background(Color::White),
+        _ => Style::Plain,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_render_item_send() {
+        let message: PrintJSON = serde_json::from_value(serde_json::json!({
+            "data": [
+                { "type": "player_id", "text": "1" },
+                { "type": "text", "text": " sent " },
+                { "type": "item_id", "text": "131072", "flags": 1, "player": 2 },
+                { "type": "text", "text": " to " },
+                { "type": "player_id", "text": "2" },
+                { "type": "text", "text": " (" },
+                { "type": "location_id", "text": "50397441", "player": 1 },
+                { "type": "text", "text": ")" },
+            ],
+            "type": "ItemSend",
+            "receiving": 2,
+            "item": { "item": 131072, "location": 50397441, "player": 1, "flags": 1 },
+        }))
+        .unwrap();
+        let names = NameResolver::new(1, [(1, "Pal".to_string())].into(), HashMap::new());
+
+        assert_eq!(
+            render_print_json(&message, &names, false),
+            "Pal sent Item 131072 to Player 2 (Location 50397441)"
+        );
+    }
+}
@@ -1,16 +1,42 @@
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
+use anyhow::{anyhow, bail, Context, Result};
 use ap_rs::protocol::HintData;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
+use serde_json::Value;
+use tokio::{
+    fs,
+    io::AsyncWriteExt,
+    sync::{broadcast, Mutex, RwLock},
+};
 
 use super::{
+    events::EVENT_BUS_CAPACITY,
     game_state::{FullGameState, GameMap},
+    phase::BotPhase,
     player::Player,
 };
 
+/// Bump this, and add a migration to `MIGRATIONS`, whenever the save format changes
+pub const CURRENT_SAVE_FORMAT_VERSION: u32 = 2;
+
+/// `MIGRATIONS[n]` upgrades a save from format version `n` to `n + 1`
+const MIGRATIONS: [fn(&mut Value) -> Result<()>; CURRENT_SAVE_FORMAT_VERSION as usize] =
+    [migrate_v0_to_v1, migrate_v1_to_v2];
+
+/// Fields added after format version 0 have no `serde(default)`. Their migrations in
+/// `MIGRATIONS` fill them in instead, so a save that skipped one fails to load rather than
+/// quietly losing data.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SaveFile {
+    /// Saves from before versioning (0.2.x) don't have this, and are version 0
+    #[serde(default)]
+    pub format_version: u32,
     pub player: Player,
     pub map: GameMap,
     pub seed: String,
@@ -18,6 +44,199 @@ pub struct SaveFile {
     pub last_checked_idx: i32,
     pub slot_id: i32,
     pub source_hint_queue: HashSet<HintData>,
+    /// The last time the gameplay loop ticked, used for catching up after downtime. Required
+    /// even though it's an `Option`, which serde would otherwise default to `None`.
+    #[serde(deserialize_with = "Option::deserialize")]
+    pub last_active: Option<DateTime<Utc>>,
+    /// How many goal items the slot needs, so saves can be inspected offline
+    #[serde(deserialize_with = "Option::deserialize")]
+    pub num_goal: Option<u16>,
+    /// K = slot, V = alias, for the players on our team
+    pub player_names: HashMap<i32, String>,
+}
+
+/// How many previous saves to keep next to the current one
+pub const SAVE_BACKUP_COUNT: usize = 3;
+
+impl SaveFile {
+    pub fn read_from(path: &Path) -> Result<Self> {
+        let file_str = std::fs::read_to_string(path)?;
+        Self::from_json_str(&file_str)
+    }
+
+    /// Parses a save of any known format version, migrating it up to the current one
+    pub fn from_json_str(file_str: &str) -> Result<Self> {
+        let mut save_json = serde_json::from_str::<Value>(file_str)?;
+
+        let version = match save_json.get("format_version") {
+            Some(version) => version
+                .as_u64()
+                .ok_or_else(|| anyhow!("Save format version is not a number: {version}"))?
+                as u32,
+            None => 0,
+        };
+
+        if version > CURRENT_SAVE_FORMAT_VERSION {
+            bail!(
+                "Save format version {version} is newer than this version of ArchipelaPal \
+                supports ({CURRENT_SAVE_FORMAT_VERSION})"
+            );
+        }
+
+        for (from_version, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            log::info!(
+                "Migrating save from format version {from_version} to {}",
+                from_version + 1
+            );
+            migration(&mut save_json)
+                .with_context(|| format!("Could not migrate save from version {from_version}"))?;
+            save_json["format_version"] = (from_version as u32 + 1).into();
+        }
+
+        Ok(serde_json::from_value(save_json)?)
+    }
+
+    /// Which team and slot the save at `path` (or failing that, one of its backups) belongs to.
+    /// Unlike `load_with_backups`, this never moves anything, so it's safe on other slots' saves.
+    pub fn peek_owner(path: &Path) -> Option<(i32, i32)> {
+        #[derive(Deserialize)]
+        struct Owner {
+            team: i32,
+            slot_id: i32,
+        }
+
+        [path.to_path_buf()]
+            .into_iter()
+            .chain((1..=SAVE_BACKUP_COUNT).map(|n| backup_path(path, n)))
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .find_map(|file_str| serde_json::from_str::<Owner>(&file_str).ok())
+            .map(|owner| (owner.team, owner.slot_id))
+    }
+
+    /// Loads the save at `path`. If it's corrupt, the corrupt file is moved aside and the newest
+    /// valid backup is used instead, or an error returned if there's none. Returns `None` if
+    /// there's nothing to load.
+    pub fn load_with_backups(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let primary_err = match Self::read_from(path) {
+            Ok(save) => return Ok(Some(save)),
+            Err(e) => e,
+        };
+
+        log::error!("Save file {} is corrupt: {primary_err}", path.display());
+
+        for n in 1..=SAVE_BACKUP_COUNT {
+            let backup = backup_path(path, n);
+            if !backup.exists() {
+                continue;
+            }
+
+            match Self::read_from(&backup) {
+                Ok(save) => {
+                    let corrupt = move_aside_corrupt(path)?;
+
+                    log::warn!("Loaded backup save {}", backup.display());
+                    println!(
+                        "WARNING: Save file {} is corrupt! Loaded the backup at {} instead. \
+                        The corrupt file was moved to {}",
+                        path.display(),
+                        backup.display(),
+                        corrupt.display()
+                    );
+                    return Ok(Some(save));
+                }
+                Err(e) => log::error!("Backup save {} is corrupt: {e}", backup.display()),
+            }
+        }
+
+        // Out of the way of the next save, which would otherwise replace it
+        let corrupt = move_aside_corrupt(path)?;
+
+        Err(primary_err.context(format!(
+            "Save file is corrupt, and no valid backup was found. It was moved to {}. Start with \
+            --recover to rebuild the game state from the server instead",
+            corrupt.display()
+        )))
+    }
+
+    /// Writes the save to a temp file, then renames it over `path`, so a crash mid-write can't
+    /// leave a truncated save behind. The previous save is rotated into the backups.
+    pub async fn write_with_backups(&self, path: &Path) -> Result<()> {
+        let savefile_json = serde_json::to_string(self)?;
+
+        let tmp_path = path.with_extension("json.tmp");
+        let mut tmp_file = fs::File::create(&tmp_path).await?;
+        tmp_file.write_all(savefile_json.as_bytes()).await?;
+        tmp_file.sync_all().await?;
+        drop(tmp_file);
+
+        if path.exists() {
+            for n in (1..SAVE_BACKUP_COUNT).rev() {
+                let from = backup_path(path, n);
+                if from.exists() {
+                    fs::rename(&from, backup_path(path, n + 1)).await?;
+                }
+            }
+            // Copy rather than rename, so there's always a save at `path`
+            fs::copy(path, backup_path(path, 1)).await?;
+        }
+
+        fs::rename(&tmp_path, path).await?;
+
+        Ok(())
+    }
+}
+
+/// Format version 0 -> 1: Adds `last_active`. Older saves never recorded it, so it's unknown.
+fn migrate_v0_to_v1(save: &mut Value) -> Result<()> {
+    let save = save
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Save is not a JSON object"))?;
+    save.entry("last_active").or_insert(Value::Null);
+
+    Ok(())
+}
+
+/// Format version 1 -> 2: Adds `num_goal` and `player_names`. These are filled in from the server
+/// once we connect.
+fn migrate_v1_to_v2(save: &mut Value) -> Result<()> {
+    let save = save
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Save is not a JSON object"))?;
+    save.entry("num_goal").or_insert(Value::Null);
+    save.entry("player_names")
+        .or_insert(Value::Object(Default::default()));
+
+    Ok(())
+}
+
+/// Moves a corrupt save to the first free of `save-file-x.json.corrupt`, `.corrupt2` and so on,
+/// so an earlier corrupt save is never replaced
+fn move_aside_corrupt(path: &Path) -> Result<PathBuf> {
+    let corrupt = corrupt_paths(path)
+        .find(|corrupt| !corrupt.exists())
+        .expect("corrupt_paths is endless");
+    std::fs::rename(path, &corrupt).context("Could not move the corrupt save file aside")?;
+
+    Ok(corrupt)
+}
+
+/// `save-file-x.json` -> `save-file-x.json.corrupt`, `save-file-x.json.corrupt2`, ...
+pub fn corrupt_paths(path: &Path) -> impl Iterator<Item = PathBuf> + '_ {
+    (1..).map(move |n| match n {
+        1 => path.with_extension("json.corrupt"),
+        n => path.with_extension(format!("json.corrupt{n}")),
+    })
+}
+
+/// `save-file-x.json` -> `save-file-x.json.bak1`
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".bak{n}"));
+    PathBuf::from(name)
 }
 
 impl From<SaveFile> for FullGameState {
@@ -26,6 +245,8 @@ impl From<SaveFile> for FullGameState {
         let map = Arc::new(RwLock::new(value.map));
         let last_checked_idx = Arc::new(RwLock::new(value.last_checked_idx));
         let source_hint_queue = Arc::new(RwLock::new(value.source_hint_queue));
+        let last_active = Arc::new(RwLock::new(value.last_active));
+        let (events, _) = broadcast::channel(EVENT_BUS_CAPACITY);
 
         Self {
             map,
@@ -35,6 +256,107 @@ impl From<SaveFile> for FullGameState {
             last_checked_idx,
             slot_id: value.slot_id,
             source_hint_queue,
+            last_active,
+            num_goal: value.num_goal,
+            player_names: value.player_names,
+            speed_override: Arc::new(RwLock::new(1.0)),
+            names: Default::default(),
+            bot_config: Default::default(),
+            events,
+            phase: Arc::new(RwLock::new(BotPhase::default())),
+            save_lock: Mutex::new(()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_0_2_save() {
+        let fixture = include_str!("../../tests/fixtures/save-file-0.2.x.json");
+        let save = SaveFile::from_json_str(fixture).unwrap();
+
+        assert_eq!(save.format_version, CURRENT_SAVE_FORMAT_VERSION);
+        assert_eq!(save.seed, "38291047561029384756");
+        assert_eq!(save.slot_id, 2);
+        assert_eq!(save.last_checked_idx, 5);
+        assert_eq!(save.player.get_num_goal_items(), 2);
+        assert_eq!(save.player.get_accessible_regions(), vec![1]);
+        assert_eq!(save.map.map.values().flatten().count(), 7);
+        assert!(save.last_active.is_none());
+        assert!(save.num_goal.is_none());
+        assert!(save.player_names.is_empty());
+    }
+
+    #[test]
+    fn test_migrations_add_fields() {
+        let fixture = include_str!("../../tests/fixtures/save-file-0.2.x.json");
+        let mut save_json = serde_json::from_str::<Value>(fixture).unwrap();
+
+        // Without its migrations, an old save is missing fields and doesn't load
+        assert!(serde_json::from_value::<SaveFile>(save_json.clone()).is_err());
+
+        migrate_v0_to_v1(&mut save_json).unwrap();
+        assert_eq!(save_json["last_active"], Value::Null);
+        assert!(save_json.get("num_goal").is_none());
+
+        migrate_v1_to_v2(&mut save_json).unwrap();
+        assert_eq!(save_json["num_goal"], Value::Null);
+        assert_eq!(save_json["player_names"], serde_json::json!({}));
+
+        // Fields that were already there are left alone
+        assert_eq!(save_json["last_checked_idx"], 5);
+        assert_eq!(save_json["player"]["speed_modifier"], 1.03);
+    }
+
+    #[test]
+    fn test_newer_save_rejected() {
+        let fixture = include_str!("../../tests/fixtures/save-file-0.2.x.json");
+        let mut save_json = serde_json::from_str::<Value>(fixture).unwrap();
+        save_json["format_version"] = (CURRENT_SAVE_FORMAT_VERSION + 1).into();
+
+        assert!(SaveFile::from_json_str(&save_json.to_string()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_backup_fallback() {
+        let dir = std::env::temp_dir().join(format!("archipelapal-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("save-file-test.json");
+
+        for idx in 1..=5 {
+            let save = SaveFile {
+                last_checked_idx: idx,
+                ..Default::default()
+            };
+            save.write_with_backups(&path).await.unwrap();
+        }
+
+        assert!(backup_path(&path, SAVE_BACKUP_COUNT).exists());
+        assert!(!backup_path(&path, SAVE_BACKUP_COUNT + 1).exists());
+
+        // Simulate a crash mid-write
+        std::fs::write(&path, "{\"player\": {").unwrap();
+
+        let loaded = SaveFile::load_with_backups(&path).unwrap().unwrap();
+        assert_eq!(loaded.last_checked_idx, 4);
+        assert!(path.with_extension("json.corrupt").exists());
+
+        // With nothing left to fall back on, it's an error, and the earlier corrupt save is kept
+        for file in [path.clone()]
+            .into_iter()
+            .chain((1..=SAVE_BACKUP_COUNT).map(|n| backup_path(&path, n)))
+        {
+            std::fs::write(&file, "{\"player\": {").unwrap();
+        }
+        let err = SaveFile::load_with_backups(&path).unwrap_err();
+        assert!(format!("{err:#}").contains("--recover"));
+        assert!(!path.exists());
+        assert!(path.with_extension("json.corrupt").exists());
+        assert!(path.with_extension("json.corrupt2").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+};
+
+use anyhow::{bail, Context, Result};
+use fs4::FileExt;
+
+use super::paths::AppPaths;
+
+/// An OS file lock (`flock`, or `LockFileEx` on Windows) on a file next to a save, so two
+/// instances can't play the same slot at once. The OS releases it when we exit, however that
+/// happens, so there are no stale locks to clear. The file also holds our PID, to say who has it.
+#[derive(Debug)]
+pub struct SaveLock {
+    // Held for the lock, which goes away with it
+    _file: File,
+}
+
+impl SaveLock {
+    /// Locks the save at `save_path`
+    pub fn acquire(save_path: &Path) -> Result<Self> {
+        Self::lock(save_path.with_extension("json.lock"))
+    }
+
+    /// Locks playing `slot_name` in `seed_name`. This is all we know before connecting, when
+    /// there's no slot ID or team to find the save with yet.
+    pub fn acquire_slot(seed_name: &str, slot_name: &str) -> Result<Self> {
+        let slot_name = slot_name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+
+        Self::lock(
+            AppPaths::get()
+                .saves_dir()
+                .join(format!("slot-{seed_name}-{slot_name}.lock")),
+        )
+    }
+
+    fn lock(path: PathBuf) -> Result<Self> {
+        // Never truncated or removed, since another instance may have it open
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Could not open save lock {}", path.display()))?;
+
+        if file.try_lock_exclusive().is_err() {
+            // Windows won't let us read a file someone else has locked
+            let owner = fs::read_to_string(&path)
+                .ok()
+                .and_then(|pid| pid.trim().parse::<u32>().ok());
+            match owner {
+                Some(pid) => {
+                    bail!("Another ArchipelaPal instance (PID {pid}) is already playing this slot!")
+                }
+                None => bail!("Another ArchipelaPal instance is already playing this slot!"),
+            }
+        }
+
+        // Only informational, the lock is what counts
+        file.set_len(0)?;
+        write!(file, "{}", process::id())?;
+
+        Ok(Self { _file: file })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_instance_refused() {
+        let dir = std::env::temp_dir().join(format!("archipelapal-lock-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let save_path = dir.join("save-file-test.json");
+
+        let lock = SaveLock::acquire(&save_path).unwrap();
+        let err = SaveLock::acquire(&save_path).unwrap_err();
+        assert!(err.to_string().contains(&process::id().to_string()));
+        drop(lock);
+
+        // A lock file left behind, even with a PID that's reused or unreadable, doesn't count
+        fs::write(save_path.with_extension("json.lock"), "garbage").unwrap();
+        let lock = SaveLock::acquire(&save_path).unwrap();
+        drop(lock);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
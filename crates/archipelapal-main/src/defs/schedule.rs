@@ -0,0 +1,299 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use anyhow::{anyhow, bail, Context, Error, Result};
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+const ALL_DAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// A set of daily windows in which the gameplay loop is allowed to run.
+/// An empty schedule is always active.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActiveSchedule {
+    pub windows: Vec<ActiveWindow>,
+}
+
+impl ActiveSchedule {
+    pub fn is_active_at(&self, now: NaiveDateTime) -> bool {
+        self.windows.is_empty() || self.windows.iter().any(|w| w.contains(now))
+    }
+
+    /// Returns how long until the schedule becomes active again, or `None` if it is active `now`
+    pub fn time_until_active(&self, now: NaiveDateTime) -> Option<Duration> {
+        if self.is_active_at(now) {
+            return None;
+        }
+
+        let next_start = self.next_start_after(now)?;
+        (next_start - now).to_std().ok()
+    }
+
+    /// How much of the time between `from` and `to` falls inside the schedule
+    pub fn active_time_between(&self, from: NaiveDateTime, to: NaiveDateTime) -> Duration {
+        if to <= from {
+            return Duration::ZERO;
+        }
+        if self.windows.is_empty() {
+            return (to - from).to_std().unwrap_or_default();
+        }
+
+        // Every window occurrence that could overlap, starting the day before for overnight ones
+        let mut occurrences = from
+            .date()
+            .pred_opt()
+            .into_iter()
+            .flat_map(|first| first.iter_days())
+            .take_while(|date| *date <= to.date())
+            .flat_map(|date| {
+                self.windows
+                    .iter()
+                    .filter(move |w| w.applies_on(date.weekday()))
+                    .map(move |w| {
+                        let start = date.and_time(w.start);
+                        let end = match w.start < w.end {
+                            true => date.and_time(w.end),
+                            false => date.and_time(w.end) + chrono::Days::new(1),
+                        };
+                        (start.max(from), end.min(to))
+                    })
+            })
+            .filter(|(start, end)| start < end)
+            .collect::<Vec<_>>();
+        occurrences.sort();
+
+        // Merge overlapping windows so no time is counted twice
+        let mut total = chrono::Duration::zero();
+        let mut covered_until = from;
+        for (start, end) in occurrences {
+            let start = start.max(covered_until);
+            if end > start {
+                total += end - start;
+                covered_until = end;
+            }
+        }
+
+        total.to_std().unwrap_or_default()
+    }
+
+    pub fn next_start_after(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        // Looking 8 days ahead covers every weekly window, including today's next week
+        (0..=7)
+            .filter_map(|offset| now.date().checked_add_days(chrono::Days::new(offset)))
+            .flat_map(|date| {
+                self.windows
+                    .iter()
+                    .filter(move |w| w.applies_on(date.weekday()))
+                    .map(move |w| date.and_time(w.start))
+            })
+            .filter(|start| *start > now)
+            .min()
+    }
+}
+
+/// A single window, e.g. `weekdays 18:00-23:00`. Windows whose end is before their start run past
+/// midnight, and belong to the day they start on.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActiveWindow {
+    /// Days this window starts on. Empty means every day.
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    #[serde(with = "hour_minute")]
+    pub start: NaiveTime,
+    #[serde(with = "hour_minute")]
+    pub end: NaiveTime,
+}
+
+impl ActiveWindow {
+    fn applies_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    fn contains(&self, now: NaiveDateTime) -> bool {
+        let time = now.time();
+        let today = now.weekday();
+
+        if self.start < self.end {
+            return self.applies_on(today) && time >= self.start && time < self.end;
+        }
+
+        // Overnight window: either the part after today's start, or the part of yesterday's
+        // window that spilled into today
+        (self.applies_on(today) && time >= self.start)
+            || (self.applies_on(today.pred()) && time < self.end)
+    }
+}
+
+impl FromStr for ActiveWindow {
+    type Err = Error;
+
+    /// Parses `[DAYS ]HH:MM-HH:MM`, where `DAYS` is `daily`, `weekdays`, `weekends`, a range like
+    /// `mon-fri`, or a comma separated list like `sat,sun`.
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (days_str, times_str) = match s.rsplit_once(' ') {
+            Some((days, times)) => (Some(days.trim()), times),
+            None => (None, s),
+        };
+
+        let days = days_str.map(parse_days).transpose()?.unwrap_or_default();
+
+        let (start, end) = times_str.split_once('-').ok_or_else(|| {
+            anyhow!("Expected a time range like 18:00-23:00, got \"{times_str}\"")
+        })?;
+        let start = parse_hour_minute(start)?;
+        let end = parse_hour_minute(end)?;
+
+        if start == end {
+            bail!("Active window \"{s}\" starts and ends at the same time");
+        }
+
+        Ok(Self { days, start, end })
+    }
+}
+
+impl fmt::Display for ActiveWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.days.is_empty() {
+            let days = self
+                .days
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            write!(f, "{days} ")?;
+        }
+
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+fn parse_days(s: &str) -> Result<Vec<Weekday>> {
+    match s.to_lowercase().as_str() {
+        "daily" => return Ok(vec![]),
+        "weekdays" => return Ok(ALL_DAYS[..5].to_vec()),
+        "weekends" => return Ok(ALL_DAYS[5..].to_vec()),
+        _ => {}
+    }
+
+    let mut days = vec![];
+    for part in s.split(',') {
+        let parse_day = |d: &str| {
+            d.trim()
+                .parse::<Weekday>()
+                .map_err(|_| anyhow!("Unknown day \"{d}\""))
+        };
+
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let mut day = parse_day(from)?;
+                let to = parse_day(to)?;
+                days.push(day);
+                while day != to {
+                    day = day.succ();
+                    days.push(day);
+                }
+            }
+            None => days.push(parse_day(part)?),
+        }
+    }
+
+    Ok(days)
+}
+
+fn parse_hour_minute(s: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(s.trim(), "%H:%M")
+        .with_context(|| format!("Invalid time \"{s}\", expected HH:MM"))
+}
+
+mod hour_minute {
+    use chrono::NaiveTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.format("%H:%M").to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        super::parse_hour_minute(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn at(day: u32, time: &str) -> NaiveDateTime {
+        // 2024-07-01 is a Monday
+        NaiveDate::from_ymd_opt(2024, 7, day)
+            .unwrap()
+            .and_time(parse_hour_minute(time).unwrap())
+    }
+
+    #[test]
+    fn test_parse_window() {
+        let window: ActiveWindow = "weekdays 18:00-23:00".parse().unwrap();
+        assert_eq!(window.days, ALL_DAYS[..5].to_vec());
+        assert_eq!(window.to_string(), "Mon,Tue,Wed,Thu,Fri 18:00-23:00");
+
+        let window: ActiveWindow = "sat-sun 10:00-12:30".parse().unwrap();
+        assert_eq!(window.days, vec![Weekday::Sat, Weekday::Sun]);
+
+        let window: ActiveWindow = "09:00-17:00".parse().unwrap();
+        assert!(window.days.is_empty());
+
+        assert!("noday 09:00-17:00".parse::<ActiveWindow>().is_err());
+        assert!("09:00-09:00".parse::<ActiveWindow>().is_err());
+    }
+
+    #[test]
+    fn test_weekday_window() {
+        let schedule = ActiveSchedule {
+            windows: vec!["weekdays 18:00-23:00".parse().unwrap()],
+        };
+
+        assert!(schedule.is_active_at(at(1, "18:00")));
+        assert!(!schedule.is_active_at(at(1, "23:00")));
+        assert!(!schedule.is_active_at(at(6, "19:00")));
+
+        // Friday night -> next Monday evening
+        let next = schedule.next_start_after(at(5, "23:30")).unwrap();
+        assert_eq!(next, at(8, "18:00"));
+    }
+
+    #[test]
+    fn test_overnight_window() {
+        let schedule = ActiveSchedule {
+            windows: vec!["fri 22:00-02:00".parse().unwrap()],
+        };
+
+        assert!(schedule.is_active_at(at(5, "23:00")));
+        assert!(schedule.is_active_at(at(6, "01:59")));
+        assert!(!schedule.is_active_at(at(1, "01:00")));
+        assert_eq!(
+            schedule.time_until_active(at(5, "21:00")),
+            Some(Duration::from_secs(3600))
+        );
+
+        // Friday 21:00 to Saturday 01:00 only overlaps the window from 22:00
+        assert_eq!(
+            schedule.active_time_between(at(5, "21:00"), at(6, "01:00")),
+            Duration::from_secs(3 * 3600)
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use std::{
+    convert::Infallible,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::paths::AppPaths;
+
+pub const SECRET_KEY_FILE: &str = "secret.key";
+
+/// Prefix for encrypted secrets, so older plaintext ones can still be read
+const ENCRYPTED_PREFIX: &str = "enc:";
+const NONCE_LEN: usize = 12;
+
+/// A password, or anything else that must never end up in a log. Use `expose` to get at it.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+impl FromStr for Secret {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.to_string()))
+    }
+}
+
+/// A secret as written to disk: encrypted with the key in the config directory, or plaintext
+/// from before secrets were encrypted. Only decrypted when it's needed, so a missing or replaced
+/// key loses that one secret rather than everything stored next to it.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StoredSecret(String);
+
+impl StoredSecret {
+    /// Encrypts `secret`, creating the key if needed. This keeps passwords out of plain sight in
+    /// `user_settings.json`, but anyone who can read the key file can decrypt them.
+    pub fn seal(secret: &Secret) -> Result<Self> {
+        Ok(Self(encrypt_with(&load_or_create_key()?, secret)?))
+    }
+
+    /// The secret, or `None` with a warning if it can't be decrypted. Never creates a key.
+    pub fn reveal(&self) -> Option<Secret> {
+        decrypt(&self.0)
+            .inspect_err(|e| log::warn!("Ignoring a stored password: {e}"))
+            .ok()
+    }
+}
+
+impl fmt::Debug for StoredSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+/// Decrypts a secret written by `StoredSecret::seal`. Anything without the encrypted prefix is
+/// taken as plaintext.
+fn decrypt(stored: &str) -> Result<Secret> {
+    if !stored.starts_with(ENCRYPTED_PREFIX) {
+        return Ok(Secret::new(stored.to_string()));
+    }
+
+    let key = load_key()?.ok_or_else(|| {
+        anyhow!("{SECRET_KEY_FILE} is missing, so stored passwords can't be decrypted")
+    })?;
+    decrypt_with(&key, stored)
+}
+
+fn encrypt_with(key: &Key, secret: &Secret) -> Result<String> {
+    let cipher = ChaCha20Poly1305::new(key);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, secret.expose().as_bytes())
+        .map_err(|_| anyhow!("Could not encrypt secret"))?;
+
+    let mut payload = nonce.to_vec();
+    payload.extend(ciphertext);
+
+    Ok(format!("{ENCRYPTED_PREFIX}{}", BASE64.encode(payload)))
+}
+
+fn decrypt_with(key: &Key, stored: &str) -> Result<Secret> {
+    let encoded = stored
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .ok_or_else(|| anyhow!("Stored secret is not encrypted"))?;
+
+    let payload = BASE64
+        .decode(encoded)
+        .context("Stored secret is not valid base64")?;
+    if payload.len() < NONCE_LEN {
+        bail!("Stored secret is too short");
+    }
+    let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+
+    let cipher = ChaCha20Poly1305::new(key);
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| {
+            anyhow!("Could not decrypt stored secret, was {SECRET_KEY_FILE} changed or deleted?")
+        })?;
+
+    Ok(Secret::new(String::from_utf8(plaintext)?))
+}
+
+fn key_file() -> PathBuf {
+    AppPaths::get().config_dir.join(SECRET_KEY_FILE)
+}
+
+/// `None` if there's no key yet
+fn load_key() -> Result<Option<Key>> {
+    let path = key_file();
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let key = BASE64.decode(fs::read_to_string(&path)?.trim())?;
+    if key.len() != 32 {
+        bail!("{} is not a valid key", path.display());
+    }
+
+    Ok(Some(*Key::from_slice(&key)))
+}
+
+fn load_or_create_key() -> Result<Key> {
+    if let Some(key) = load_key()? {
+        return Ok(key);
+    }
+
+    let path = key_file();
+    let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+    write_private_file(&path, &BASE64.encode(key))
+        .with_context(|| format!("Could not write {}", path.display()))?;
+    log::info!("Created secret key {}", path.display());
+
+    Ok(key)
+}
+
+/// Deletes the key, which makes every stored secret unreadable
+pub fn delete_key() -> Result<()> {
+    let path = key_file();
+    if path.exists() {
+        fs::remove_file(&path).with_context(|| format!("Could not delete {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Writes a file only we can read, where the platform supports it
+fn write_private_file(path: &Path, contents: &str) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::{io::Write, os::unix::fs::OpenOptionsExt};
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(path)?;
+        file.write_all(contents.as_bytes())?;
+    }
+
+    #[cfg(not(unix))]
+    fs::write(path, contents)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_round_trip() {
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let secret = Secret::new("hunter2".to_string());
+
+        let stored = encrypt_with(&key, &secret).unwrap();
+        assert!(!stored.contains("hunter2"));
+        assert_eq!(decrypt_with(&key, &stored).unwrap(), secret);
+
+        let other_key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        assert!(decrypt_with(&other_key, &stored).is_err());
+
+        assert_eq!(decrypt("plain").unwrap().expose(), "plain");
+        assert_eq!(format!("{secret:?}"), "<redacted>");
+    }
+}
@@ -0,0 +1,71 @@
+use super::lib::RegionID;
+
+/// Flavor text for a `game_theme` from the slot data. Only changes how things are worded, never
+/// what the bot does. Regions keep their numbers, since the apworld doesn't name them.
+#[derive(Debug)]
+pub struct Theme {
+    pub chest_noun: &'static str,
+    pub check_verb: &'static str,
+}
+
+pub const ADVENTURE: Theme = Theme {
+    chest_noun: "chest",
+    check_verb: "Searched",
+};
+
+/// For themes this version doesn't know about
+pub const PLAIN: Theme = Theme {
+    chest_noun: "chest",
+    check_verb: "Checked",
+};
+
+impl Theme {
+    /// Matches the `game_theme` option in the apworld
+    pub fn from_id(game_theme: u8) -> &'static Theme {
+        match game_theme {
+            0 => &ADVENTURE,
+            _ => {
+                log::debug!("Unknown game theme {game_theme}, using plain names");
+                &PLAIN
+            }
+        }
+    }
+
+    pub fn region_name(&self, region: RegionID) -> String {
+        match region {
+            0 => "Hub".to_string(),
+            region => format!("Region {region}"),
+        }
+    }
+
+    /// e.g. "the Hub" or "Region 4", for use in a sentence
+    pub fn describe_region(&self, region: RegionID) -> String {
+        match region {
+            0 => "the Hub".to_string(),
+            region => format!("Region {region}"),
+        }
+    }
+
+    /// e.g. "Searched a chest in Region 4"
+    pub fn describe_check(&self, region: RegionID) -> String {
+        format!(
+            "{} a {} in {}",
+            self.check_verb,
+            self.chest_noun,
+            self.describe_region(region)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_check() {
+        assert_eq!(ADVENTURE.describe_check(4), "Searched a chest in Region 4");
+        assert_eq!(ADVENTURE.describe_check(0), "Searched a chest in the Hub");
+        assert_eq!(PLAIN.describe_check(4), "Checked a chest in Region 4");
+        assert_eq!(PLAIN.region_name(0), "Hub");
+    }
+}
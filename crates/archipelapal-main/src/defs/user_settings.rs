@@ -1,29 +1,89 @@
-use std::fs;
+use std::{collections::BTreeMap, fs};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 
+use super::{paths::AppPaths, schedule::ActiveSchedule, secret::StoredSecret};
+
 pub const USER_SETTINGS_FILE: &str = "user_settings.json";
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct UserSettings {
+    /// Set when the settings file exists but couldn't be read, so we don't overwrite it
+    #[serde(skip)]
+    read_only: bool,
     pub last_used_address: Option<String>,
     pub last_used_slot: Option<String>,
+    /// When set, the gameplay loop only runs inside these windows
+    #[serde(default)]
+    pub active_hours: Option<ActiveSchedule>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ConnectionProfile>,
+    #[serde(default)]
+    pub last_used_profile: Option<String>,
+}
+
+/// A named room to connect to, for running in more than one room at a time
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConnectionProfile {
+    pub address: String,
+    pub slot: String,
+    /// Only kept if asked to, and encrypted when written to disk
+    #[serde(default)]
+    pub password: Option<StoredSecret>,
+    #[serde(default)]
+    pub options: ProfileOptions,
+}
+
+/// Bot options used instead of the global ones when playing with a profile
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ProfileOptions {
+    #[serde(default)]
+    pub active_hours: Option<ActiveSchedule>,
+    #[serde(default)]
+    pub catch_up: bool,
+    #[serde(default)]
+    pub skip_start_confirmation: bool,
 }
 
 impl UserSettings {
     pub fn load() -> Result<Self> {
-        Ok(fs::read_to_string(USER_SETTINGS_FILE)
+        Ok(fs::read_to_string(AppPaths::get().user_settings_file())
             .and_then(|file_str| Ok(serde_json::from_str::<UserSettings>(&file_str)?))?)
     }
 
+    /// A missing file gives the defaults. An unreadable one does too, but they can't be saved
+    /// over it.
     pub fn load_or_default() -> Self {
-        Self::load().unwrap_or_default()
+        let path = AppPaths::get().user_settings_file();
+        if !path.exists() {
+            return Self::default();
+        }
+
+        Self::load().unwrap_or_else(|e| {
+            log::error!("Could not read {}: {e}", path.display());
+            println!(
+                "WARNING: Could not read {}, so it won't be changed. Fix or delete it to save \
+                settings again.",
+                path.display()
+            );
+            Self {
+                read_only: true,
+                ..Default::default()
+            }
+        })
     }
 
     pub fn save(&self) -> Result<()> {
+        if self.read_only {
+            bail!(
+                "Not overwriting {}, since it couldn't be read",
+                AppPaths::get().user_settings_file().display()
+            );
+        }
+
         let savefile_json = serde_json::to_string(self)?;
-        fs::write(USER_SETTINGS_FILE, savefile_json)?;
+        fs::write(AppPaths::get().user_settings_file(), savefile_json)?;
 
         Ok(())
     }
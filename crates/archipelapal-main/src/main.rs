@@ -1,40 +1,169 @@
 use std::{
-    fs,
-    io::{stdin, stdout, Write},
+    collections::{HashMap, HashSet},
+    io::{stdin, stdout, IsTerminal, Write},
+    path::PathBuf,
     sync::Arc,
+    time::Duration,
     vec,
 };
 
 use anyhow::{anyhow, bail, Context, Result};
-use ap_rs::{client::ArchipelagoClient, protocol::Get};
-use clap::Parser;
+use ap_rs::{
+    client::ArchipelagoClient,
+    protocol::{Get, GetDataPackage},
+};
+use clap::{Parser, Subcommand};
+use commands::{
+    saves::{run_saves_command, SavesCommand},
+    settings::{run_settings_command, SettingsCommand},
+    simulate::{run_simulate_command, SimulateArgs},
+    validate::{run_validate_command, ValidateArgs},
+};
 use defs::{
+    bot_config::BotConfig,
+    control::ControlCommand,
+    data_package::{self, GameNames},
     game_state::{FullGameState, GameMap},
-    lib::{ArchipelaPalSlotData, GoalOneShotData, SAVE_FILE_DIRECTORY},
-    user_settings::UserSettings,
+    input,
+    lib::{ArchipelaPalSlotData, GoalOneShotData},
+    logging::{self, LogFormat},
+    names::NameResolver,
+    paths::AppPaths,
+    phase::BotPhase,
+    save_file::SaveFile,
+    save_lock::SaveLock,
+    schedule::{ActiveSchedule, ActiveWindow},
+    secret::{Secret, StoredSecret},
+    theme::Theme,
+    user_settings::{ConnectionProfile, UserSettings},
 };
 use processes::{
-    game_playing_thread::spawn_game_playing_task, message_handler::spawn_ap_server_task,
+    config_watcher::spawn_config_watcher_task,
+    console_output::spawn_console_output_task,
+    dashboard::spawn_dashboard_task,
+    event_log::spawn_event_log_task,
+    game_playing_thread::{spawn_game_playing_task, GameplayOptions},
+    journal::spawn_journal_task,
+    message_handler::spawn_ap_server_task,
+    operator_console::spawn_operator_console_task,
+    save_writer::spawn_save_writer_task,
 };
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc, oneshot, RwLock};
 
+mod commands;
 mod defs;
 mod processes;
 mod utils;
 
 #[derive(Parser)]
-struct Args {
+#[clap(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// Running without a subcommand is the same as `play`. These can't be mixed with a
+    /// subcommand, so `--slot-name x saves list` is an error rather than ignoring `--slot-name`.
+    #[clap(flatten)]
+    play: PlayArgs,
+
+    /// Where to keep save files. Defaults to `$XDG_DATA_HOME/archipelapal` on Linux, and the
+    /// current directory elsewhere
+    #[clap(long, env = "ARCHIPELAPAL_DATA_DIR", global = true)]
+    data_dir: Option<PathBuf>,
+
+    /// Where to keep user settings. Defaults to `$XDG_CONFIG_HOME/archipelapal` on Linux, and the
+    /// current directory elsewhere
+    #[clap(long, env = "ARCHIPELAPAL_CONFIG_DIR", global = true)]
+    config_dir: Option<PathBuf>,
+
+    /// How to format log lines, on stderr and in the log file in the data directory
+    #[clap(
+        long,
+        env = "ARCHIPELAPAL_LOG_FORMAT",
+        global = true,
+        value_enum,
+        default_value_t = LogFormat::Text
+    )]
+    log_format: LogFormat,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Connect to an AP server and play (the default)
+    Play(PlayArgs),
+    /// Manage save files
+    #[clap(subcommand)]
+    Saves(SavesCommand),
+    /// Manage user settings
+    #[clap(subcommand)]
+    Settings(SettingsCommand),
+    /// Play a game offline, with every item in our own world, to see how long some options take
+    Simulate(SimulateArgs),
+    /// Check a save file for problems
+    Validate(ValidateArgs),
+}
+
+#[derive(clap::Args)]
+struct PlayArgs {
+    /// Connect using a saved profile. Without this, or a slot name and server address, you'll be
+    /// asked to pick one
+    #[clap(long, env = "ARCHIPELAPAL_PROFILE")]
+    profile: Option<String>,
+
+    /// Save this connection and its options as a profile with the given name
+    #[clap(long, value_name = "NAME")]
+    save_profile: Option<String>,
+
     #[clap(long, short, env)]
     slot_name: Option<String>,
 
     #[clap(long, short = 'a', env)]
     server_addr: Option<String>,
 
-    #[clap(long, short, env)]
-    password: Option<String>,
+    #[clap(long, short, env, hide_env_values = true)]
+    password: Option<Secret>,
+
+    /// Remember the password in the profile being played or saved. It's stored encrypted, with a
+    /// key kept next to the user settings
+    #[clap(long)]
+    remember_password: bool,
 
     #[clap(long)]
     skip_start_confirmation: bool,
+
+    /// Only play inside this daily window, e.g. "weekdays 18:00-23:00". Can be given multiple
+    /// times, and is remembered in the user settings (or the profile, if playing with one)
+    #[clap(long, value_name = "WINDOW")]
+    active_hours: Vec<ActiveWindow>,
+
+    /// Forget any saved active hours, and play at all times
+    #[clap(long, conflicts_with = "active_hours")]
+    clear_active_hours: bool,
+
+    /// On startup, quickly make the checks that would have happened while the bot was offline,
+    /// counting only time inside the active hours. At most 50 are made this way
+    #[clap(long, env)]
+    catch_up: bool,
+
+    /// Ignore any existing save, and rebuild the game state entirely from the server
+    #[clap(long)]
+    recover: bool,
+
+    /// Show a full-screen dashboard instead of plain output, if running in a terminal
+    #[clap(long, env = "ARCHIPELAPAL_TUI")]
+    tui: bool,
+
+    /// Pick each chest yourself from the console. If you don't answer in time, the bot picks one
+    #[clap(long, env = "ARCHIPELAPAL_COPILOT")]
+    copilot: bool,
+
+    /// The bot config file to use. Defaults to `archipelapal.toml` in the config directory
+    #[clap(long, env = "ARCHIPELAPAL_BOT_CONFIG", value_name = "PATH")]
+    bot_config: Option<PathBuf>,
+
+    /// Write the save file at most this often, in seconds
+    #[clap(long, env, default_value_t = 5)]
+    save_interval: u64,
 }
 
 pub const GAME_NAME: &str = "ArchipelaPal";
@@ -42,10 +171,16 @@ pub const ITEM_HANDLING: i32 = 0b111;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let main_result = outer_main().await;
+    dotenvy::dotenv().ok();
+
+    let cli = Cli::parse();
+    let interactive = matches!(cli.command, None | Some(Command::Play(_)));
+
+    let main_result = outer_main(cli).await;
 
     match main_result {
         Ok(_) => Ok(()),
+        Err(e) if !interactive => bail!(e),
         Err(e) => {
             log::error!("{e}");
             println!("{e}");
@@ -57,15 +192,57 @@ async fn main() -> Result<()> {
 
 // This is our primary `main` function, but to allow for easy error handling and logging, we have
 // the actual main function call this one.
-async fn outer_main() -> Result<()> {
-    dotenvy::dotenv().ok();
-    env_logger::init();
+async fn outer_main(cli: Cli) -> Result<()> {
+    let paths = AppPaths::resolve(cli.data_dir, cli.config_dir).init();
+    paths.create_dirs()?;
+    logging::init(cli.log_format, &paths.log_dir())?;
+    log::info!(
+        "Data directory: {}, config directory: {}",
+        paths.data_dir.display(),
+        paths.config_dir.display()
+    );
+
+    match cli.command {
+        None => play(cli.play).await,
+        Some(Command::Play(args)) => play(args).await,
+        Some(Command::Saves(command)) => run_saves_command(command),
+        Some(Command::Settings(command)) => run_settings_command(command),
+        Some(Command::Simulate(args)) => run_simulate_command(args).await,
+        Some(Command::Validate(args)) => run_validate_command(args),
+    }
+}
+
+async fn play(args: PlayArgs) -> Result<()> {
+    AppPaths::get()
+        .migrate_from_cwd()
+        .inspect_err(|e| log::error!("Unable to migrate old saves and settings: {e}"))
+        .ok();
 
-    let args = Args::parse();
     let mut user_settings = UserSettings::load_or_default();
 
+    let has_connection_args = args.slot_name.is_some() && args.server_addr.is_some();
+    let profile_name = match args.profile {
+        Some(name) => Some(name),
+        None if has_connection_args || user_settings.profiles.is_empty() => None,
+        None => pick_profile(&user_settings)?,
+    };
+    let mut profile = profile_name
+        .as_ref()
+        .map(|name| {
+            user_settings
+                .profiles
+                .get(name)
+                .cloned()
+                .ok_or_else(|| anyhow!("There's no profile named \"{name}\""))
+        })
+        .transpose()?;
+    if let Some(name) = &profile_name {
+        println!("Using profile \"{name}\"");
+    }
+
     let slot_name = args
         .slot_name
+        .or_else(|| profile.as_ref().map(|profile| profile.slot.clone()))
         .or_else(|| {
             let last_slot_name = &user_settings.last_used_slot;
             let prompt = match last_slot_name {
@@ -84,6 +261,7 @@ async fn outer_main() -> Result<()> {
 
     let addr = args
         .server_addr
+        .or_else(|| profile.as_ref().map(|profile| profile.address.clone()))
         .or_else(|| {
             let last_server_addr = &user_settings.last_used_address;
             let prompt = match last_server_addr {
@@ -104,19 +282,105 @@ async fn outer_main() -> Result<()> {
 
     let password = args
         .password
-        .unwrap_or_else(|| get_user_input("Enter server password (Press Enter if none):").unwrap());
+        .or_else(|| {
+            profile
+                .as_ref()
+                .and_then(|profile| profile.password.as_ref())
+                .and_then(StoredSecret::reveal)
+        })
+        .map_or_else(
+            || get_password_input("Enter server password (Press Enter if none):"),
+            Ok,
+        )?;
+
+    // A profile's options replace the global ones entirely
+    let active_hours = match &mut profile {
+        Some(profile) => &mut profile.options.active_hours,
+        None => &mut user_settings.active_hours,
+    };
+    if args.clear_active_hours {
+        *active_hours = None;
+    } else if !args.active_hours.is_empty() {
+        *active_hours = Some(ActiveSchedule {
+            windows: args.active_hours,
+        });
+    }
+    let active_hours = active_hours.clone();
+
+    if let Some(schedule) = &active_hours {
+        let windows = schedule
+            .windows
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!("Active hours: {windows}");
+    }
+
+    let bot_config_path = args
+        .bot_config
+        .unwrap_or_else(|| AppPaths::get().bot_config_file());
+    let bot_config = BotConfig::load(&bot_config_path)?;
+    log::debug!("Bot config: {bot_config:?}");
+
+    let profile_options = profile
+        .as_ref()
+        .map(|profile| profile.options.clone())
+        .unwrap_or_default();
+    let catch_up = args.catch_up || profile_options.catch_up || bot_config.startup.catch_up;
+    let skip_start_confirmation =
+        args.skip_start_confirmation || profile_options.skip_start_confirmation;
+
+    // Only passwords we were asked to remember, or that a profile already had, are kept
+    let remembered_password = match args.remember_password {
+        true => Some(&password)
+            .filter(|password| !password.is_empty())
+            .map(StoredSecret::seal)
+            .transpose()?,
+        false => profile
+            .as_ref()
+            .and_then(|profile| profile.password.clone()),
+    };
+    if let (Some(name), Some(mut profile)) = (&profile_name, profile) {
+        profile.password = remembered_password.clone();
+        user_settings.profiles.insert(name.clone(), profile);
+    }
+    // A newly saved profile is the one to use next time
+    let mut last_used_profile = profile_name;
+    if let Some(name) = args.save_profile {
+        let mut options = profile_options;
+        options.active_hours = active_hours.clone();
+        options.catch_up = catch_up;
+        options.skip_start_confirmation = skip_start_confirmation;
+
+        let profile = ConnectionProfile {
+            address: addr.clone(),
+            slot: slot_name.clone(),
+            password: remembered_password,
+            options,
+        };
+        user_settings.profiles.insert(name.clone(), profile);
+        println!("Saved profile \"{name}\"");
+        last_used_profile = Some(name);
+    }
+    user_settings.last_used_profile = last_used_profile;
 
-    user_settings
-        .save()
-        .context("Could not save user settings")?;
+    if let Err(e) = user_settings.save() {
+        log::error!("Could not save user settings: {e}");
+        println!("WARNING: Could not save user settings: {e}");
+    }
 
     let mut client = ArchipelagoClient::new(&addr).await?;
 
+    // Held until we exit, so nobody else can play this slot. Taken before connecting, so a second
+    // instance never gets as far as talking to the server.
+    let _slot_lock = SaveLock::acquire_slot(&client.room_info().seed_name, &slot_name)?;
+
     let connected_packet = client
         .connect(
             GAME_NAME,
             &slot_name,
-            Some(&password),
+            Some(password.expose()),
             Some(ITEM_HANDLING), // ?
             vec!["AP".into(), "Pal".into()],
             true,
@@ -131,16 +395,26 @@ async fn outer_main() -> Result<()> {
     log::info!("Connected");
 
     let info = client.room_info();
+    logging::set_context(&config.slot_name, &info.seed_name);
     log::info!("Seed: {}", info.seed_name);
 
-    // Make 'Saves' directory if it doesn't exist
-    fs::create_dir_all(SAVE_FILE_DIRECTORY).context("Could not create 'Saves' directory")?;
-
-    let mut game_state = FullGameState::from_file_or_default(&info.seed_name);
-
     let slot_id = connected_packet.slot;
     let team = connected_packet.team;
 
+    // And the save itself, so `saves delete` leaves it alone while we play
+    let _save_lock = SaveLock::acquire(&FullGameState::make_save_file_name(
+        &info.seed_name,
+        team,
+        slot_id,
+    ))?;
+
+    let mut game_state = if args.recover {
+        log::info!("Ignoring any save file, recovering state from the server");
+        FullGameState::from(SaveFile::default())
+    } else {
+        FullGameState::from_file_or_default(&info.seed_name, team, slot_id)?
+    };
+
     // Correct the game state if it ended up being a default
     if game_state.seed_name.is_empty() {
         let game_map = GameMap::new_from_config(&config);
@@ -155,15 +429,79 @@ async fn outer_main() -> Result<()> {
         game_state.slot_id = slot_id;
     }
 
+    game_state.num_goal = Some(config.num_goal);
+    game_state.player_names = connected_packet
+        .players
+        .iter()
+        .filter(|player| player.team == team)
+        .map(|player| (player.slot, player.alias.clone()))
+        .collect();
+    let slot_games = connected_packet
+        .slot_info
+        .iter()
+        .filter_map(|(slot, info)| Some((slot.parse::<i32>().ok()?, info.game.clone())))
+        .collect::<HashMap<_, _>>();
+    let mut names = NameResolver::new(slot_id, game_state.player_names.clone(), slot_games);
+    names.theme = Theme::from_id(config.game_theme);
+
+    // Names for the games in this room, from the cache where we can, and the server otherwise
+    let mut uncached_games = vec![];
+    for game in names.slot_games.values().cloned().collect::<HashSet<_>>() {
+        let cached = info
+            .datapackage_checksums
+            .get(&game)
+            .and_then(|checksum| data_package::load_cached(checksum));
+        match cached {
+            Some(data) => names.add_game(game, GameNames::from(&data)),
+            None => uncached_games.push(game),
+        }
+    }
+    game_state.names = Arc::new(RwLock::new(names));
+
+    // The server knows best which of our locations were checked, whether we have no save at all,
+    // or we crashed before our last checks were saved. Our inventory and hints are rebuilt once
+    // the server task gets the full `Sync` and the hints key.
+    let newly_checked = game_state
+        .map
+        .write()
+        .await
+        .apply_checked_locations(&connected_packet.checked_locations);
+    if newly_checked > 0 {
+        println!("Recovered {newly_checked} checked locations from the server");
+    }
+
+    game_state.bot_config = Arc::new(RwLock::new(bot_config));
+
     let game_state = Arc::new(game_state);
 
     let (mut client_sender, client_receiver) = client.split();
 
+    let use_dashboard = args.tui && stdout().is_terminal() && stdin().is_terminal();
+    if args.tui && !use_dashboard {
+        println!("Not running in a terminal, using plain output instead of the dashboard");
+    }
+    let copilot = args.copilot && !use_dashboard;
+    if args.copilot && !copilot {
+        println!("Co-pilot mode needs the console, so it's off while the dashboard is shown");
+    }
+    if !use_dashboard {
+        spawn_console_output_task(game_state.clone());
+    }
+    spawn_journal_task(game_state.clone());
+    spawn_event_log_task(game_state.clone());
+    spawn_save_writer_task(game_state.clone(), Duration::from_secs(args.save_interval));
+    spawn_config_watcher_task(game_state.clone(), bot_config_path);
+    game_state
+        .set_phase(&mut client_sender, BotPhase::Connected)
+        .await
+        .context("Could not send connected status")?;
+
     let (goal_tx, goal_rx) = oneshot::channel::<GoalOneShotData>();
 
     // Spawn server listen thread
     let server_handle =
         spawn_ap_server_task(game_state.clone(), client_receiver, config.clone(), goal_tx);
+    println!("Now listening for AP server messages");
 
     // Task started, slight delay, then send syncing packets
     client_sender
@@ -181,29 +519,210 @@ async fn outer_main() -> Result<()> {
         .await
         .context("Could not send sync packet!")?;
 
-    if !args.skip_start_confirmation {
+    if !uncached_games.is_empty() {
+        log::info!("Fetching the DataPackage for {}", uncached_games.join(", "));
+        client_sender
+            .send(ap_rs::protocol::ClientMessage::GetDataPackage(
+                GetDataPackage {
+                    games: Some(uncached_games),
+                },
+            ))
+            .await
+            .context("Could not request the DataPackage")?;
+    }
+
+    game_state
+        .set_phase(&mut client_sender, BotPhase::AwaitingStart)
+        .await
+        .context("Could not send ready status")?;
+
+    if !skip_start_confirmation {
         // Prompt user to start game "press enter to start"
         let start_prompt = format!("Press Enter to start {GAME_NAME} for slot {slot_name}...");
         get_user_input(&start_prompt)?;
     }
 
-    let game_handle =
-        spawn_game_playing_task(game_state.clone(), client_sender, config.clone(), goal_rx);
+    let (control_tx, control_rx) = mpsc::channel::<ControlCommand>(16);
+
+    // Either one ends when the user quits
+    let mut ui_handle = match use_dashboard {
+        true => spawn_dashboard_task(game_state.clone(), config.clone())?,
+        false => spawn_operator_console_task(game_state.clone(), control_tx),
+    };
+
+    let game_handle = spawn_game_playing_task(
+        game_state.clone(),
+        client_sender,
+        config.clone(),
+        goal_rx,
+        control_rx,
+        GameplayOptions {
+            active_hours,
+            catch_up,
+            copilot,
+        },
+    );
+
+    let tasks = async {
+        let (sh_joined, gh_joined) = tokio::join!(server_handle, game_handle);
+
+        sh_joined?;
+        gh_joined?;
+
+        Ok(())
+    };
+
+    let result = tokio::select! {
+        result = tasks => result,
+        _ = tokio::signal::ctrl_c() => {
+            log::info!("Ctrl+C pressed, shutting down");
+            Ok(())
+        }
+        _ = &mut ui_handle => {
+            log::info!("Quit by the user, shutting down");
+            Ok(())
+        }
+    };
 
-    let (sh_joined, gh_joined) = tokio::join!(server_handle, game_handle);
+    // Put the terminal back before anything else is printed
+    if !ui_handle.is_finished() {
+        ui_handle.abort();
+        ui_handle.await.ok();
+    }
+    println!("Shutting down...");
 
-    sh_joined?;
-    gh_joined?;
+    // Make sure nothing the save writer hasn't gotten to yet is lost
+    game_state
+        .write_save_file()
+        .await
+        .context("Could not write save file")?;
 
-    Ok(())
+    result
 }
 
-fn get_user_input(prompt: &str) -> Result<String> {
-    let mut buf = String::new();
-    let sin = stdin();
+/// Lists the saved profiles, and asks which one to use. Returns `None` to connect without one.
+fn pick_profile(user_settings: &UserSettings) -> Result<Option<String>> {
+    let names = user_settings.profiles.keys().collect::<Vec<_>>();
+
+    println!("Profiles:");
+    for (idx, (name, profile)) in user_settings.profiles.iter().enumerate() {
+        println!(
+            "  {}. {name}: {} @ {}",
+            idx + 1,
+            profile.slot,
+            profile.address
+        );
+    }
+
+    let last_used = user_settings
+        .last_used_profile
+        .as_ref()
+        .filter(|name| user_settings.profiles.contains_key(*name));
+    let prompt = match last_used {
+        Some(name) => format!(
+            "Pick a profile by number or name (Press Enter for last used: \"{name}\", \
+            or \"new\" to connect without one):"
+        ),
+        None => {
+            "Pick a profile by number or name (Press Enter to connect without one):".to_string()
+        }
+    };
+
+    loop {
+        let input = get_user_input(&prompt)?;
+        let picked = match input.as_str() {
+            "" => return Ok(last_used.cloned()),
+            "new" => return Ok(None),
+            input => input
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| names.get(number.checked_sub(1)?))
+                .or_else(|| names.iter().find(|name| name.as_str() == input)),
+        };
+
+        match picked {
+            Some(name) => return Ok(Some(name.to_string())),
+            None => println!("There's no profile \"{input}\""),
+        }
+    }
+}
+
+/// Like `get_user_input`, but doesn't echo what's typed. Falls back to plain input if there's no
+/// terminal to read from.
+fn get_password_input(prompt: &str) -> Result<Secret> {
+    match rpassword::prompt_password(prompt) {
+        Ok(password) => Ok(Secret::new(password.trim().to_string())),
+        Err(e) => {
+            log::warn!("Could not read password without echo: {e}");
+            get_user_input(prompt).map(Secret::new)
+        }
+    }
+}
+
+pub(crate) fn get_user_input(prompt: &str) -> Result<String> {
     print!("{prompt}");
     stdout().flush()?;
-    sin.read_line(&mut buf)?;
+    let line = input::read_line()?;
+
+    Ok(line.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_play_args_without_subcommand() {
+        let cli = Cli::try_parse_from(["ArchipelaPal", "--slot-name", "Pal"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.play.slot_name.as_deref(), Some("Pal"));
+
+        let cli = Cli::try_parse_from(["ArchipelaPal", "play", "--slot-name", "Pal"]).unwrap();
+        let Some(Command::Play(args)) = cli.command else {
+            panic!("Expected play");
+        };
+        assert_eq!(args.slot_name.as_deref(), Some("Pal"));
+    }
 
-    Ok(buf.trim().to_string())
+    #[test]
+    fn test_play_args_conflict_with_subcommands() {
+        assert!(
+            Cli::try_parse_from(["ArchipelaPal", "--slot-name", "Pal", "saves", "list"]).is_err()
+        );
+        // Global options still go anywhere
+        assert!(Cli::try_parse_from(["ArchipelaPal", "saves", "list", "--data-dir", "x"]).is_ok());
+    }
+
+    #[test]
+    fn test_saves_delete_args() {
+        let cli = Cli::try_parse_from([
+            "ArchipelaPal",
+            "saves",
+            "delete",
+            "1234",
+            "--team",
+            "0",
+            "--slot",
+            "2",
+            "-y",
+        ])
+        .unwrap();
+        let Some(Command::Saves(SavesCommand::Delete { selector, yes })) = cli.command else {
+            panic!("Expected saves delete");
+        };
+        assert_eq!(selector.seed, "1234");
+        assert_eq!(selector.team, Some(0));
+        assert_eq!(selector.slot, Some(2));
+        assert!(yes);
+
+        // The seed is required
+        assert!(Cli::try_parse_from(["ArchipelaPal", "saves", "delete", "--yes"]).is_err());
+    }
+
+    #[test]
+    fn test_validate_args() {
+        let cli = Cli::try_parse_from(["ArchipelaPal", "validate", "save.json"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Validate(_))));
+        assert!(Cli::try_parse_from(["ArchipelaPal", "validate"]).is_err());
+    }
 }
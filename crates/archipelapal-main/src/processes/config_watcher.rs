@@ -0,0 +1,60 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use tokio::task::JoinHandle;
+
+use crate::defs::{bot_config::BotConfig, events::BotEvent, game_state::FullGameState};
+
+/// How often to look for changes to the bot config
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Reloads the bot config whenever the file at `path` changes. Invalid configs are ignored, and
+/// the last good one is kept. So is deleting the file, which would otherwise load the defaults.
+pub fn spawn_config_watcher_task(game_state: Arc<FullGameState>, path: PathBuf) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut last_modified = modified_time(&path);
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let modified = modified_time(&path);
+            if modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+
+            if modified.is_none() {
+                log::warn!(
+                    "Bot config {} is gone, keeping the current settings",
+                    path.display()
+                );
+                continue;
+            }
+
+            match BotConfig::load(&path) {
+                Ok(config) => {
+                    let needs_restart = game_state.bot_config.write().await.apply_reload(config);
+                    log::info!("Reloaded bot config {}", path.display());
+                    game_state.emit(BotEvent::ConfigReloaded { needs_restart });
+                }
+                Err(e) => {
+                    log::error!("Could not reload bot config: {e:#}");
+                    game_state.emit(BotEvent::ConfigReloadFailed {
+                        error: format!("{e:#}"),
+                    });
+                }
+            }
+        }
+    })
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -0,0 +1,166 @@
+use std::{
+    io::{stdout, IsTerminal},
+    sync::Arc,
+};
+
+use tokio::{sync::broadcast::error::RecvError, task::JoinHandle};
+
+use crate::{
+    defs::{
+        bot_config::NotificationConfig, events::BotEvent, game_state::FullGameState,
+        names::NameResolver, phase::BotPhase, print_json::render_print_json,
+    },
+    utils::get_region_from_loc_id,
+};
+
+/// Prints a line to the terminal for each event on the bus
+pub fn spawn_console_output_task(game_state: Arc<FullGameState>) -> JoinHandle<()> {
+    let mut events = game_state.subscribe();
+    tokio::spawn(async move {
+        let colored = stdout().is_terminal();
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(n)) => {
+                    log::warn!("Console output fell behind, skipped {n} events");
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            };
+
+            let notifications = game_state.bot_config.read().await.notifications.clone();
+            if should_ring_bell(&notifications, &event) {
+                print!("\x07");
+            }
+
+            let names = game_state.names.read().await;
+            if let Some(line) = describe_event(event, &names, colored) {
+                println!("{line}");
+            }
+        }
+    })
+}
+
+pub fn should_ring_bell(notifications: &NotificationConfig, event: &BotEvent) -> bool {
+    match event {
+        BotEvent::BKStarted => notifications.bell_on_bk,
+        BotEvent::KeyUnlocked { .. } => notifications.bell_on_key,
+        BotEvent::Goal => notifications.bell_on_goal,
+        _ => false,
+    }
+}
+
+/// A line describing `event` for humans, or `None` if it's only worth a debug log. Server messages
+/// are colored if `colored` is set.
+pub fn describe_event(event: BotEvent, names: &NameResolver, colored: bool) -> Option<String> {
+    let line = match event {
+        BotEvent::PhaseChanged { from, to } => match (from, to) {
+            (_, BotPhase::Connected) => "Connected to the AP server".to_string(),
+            (BotPhase::Connected | BotPhase::AwaitingStart, BotPhase::Playing) => {
+                "Searching for items...".to_string()
+            }
+            (_, BotPhase::Paused) => "Paused".to_string(),
+            (BotPhase::Paused, BotPhase::Playing) => "Resumed, searching for items...".to_string(),
+            _ => {
+                log::debug!("Phase changed: {from} -> {to}");
+                return None;
+            }
+        },
+        BotEvent::ItemReceived {
+            item_id,
+            sender,
+            location,
+            index,
+        } => {
+            log::debug!(
+                "Received {} from {} at {}, index {index}",
+                names.item(item_id as i32, names.own_slot),
+                names.player(sender),
+                names.location(location, sender)
+            );
+            return None;
+        }
+        BotEvent::KeyUnlocked { region } => {
+            format!("Found the key to {}!", names.theme.describe_region(region))
+        }
+        BotEvent::LocationChecked { location_id } => {
+            log::debug!(
+                "Checked {}",
+                names.location(location_id as i32, names.own_slot)
+            );
+            names
+                .theme
+                .describe_check(get_region_from_loc_id(location_id))
+        }
+        BotEvent::HintAdded { hint } => format!(
+            "Hinted {} at {} for {}",
+            names.item(hint.item.item, hint.receiving_player),
+            names.location(hint.item.location, names.own_slot),
+            names.player(hint.receiving_player)
+        ),
+        BotEvent::LocationUnchecked { location_id } => {
+            log::debug!(
+                "Unchecked {}, to retry it later",
+                names.location(location_id as i32, names.own_slot)
+            );
+            return None;
+        }
+        BotEvent::ItemIndexSynced { index } => {
+            log::debug!("Synced with the server, up to item index {index}");
+            return None;
+        }
+        BotEvent::HintRemoved { location } => {
+            log::debug!(
+                "Hint for {} resolved",
+                names.location(location, names.own_slot)
+            );
+            return None;
+        }
+        BotEvent::SpeedChanged { to, .. } => format!("Speed modifier is now {to:.2}x"),
+        BotEvent::WaitStarted { wait_ms } => {
+            log::debug!("Next check in {wait_ms} ms");
+            return None;
+        }
+        BotEvent::ChestChoiceOffered { locations, .. } => {
+            // The operator console lists these itself
+            log::debug!("Offered {} chests to pick from", locations.len());
+            return None;
+        }
+        BotEvent::BKStarted => "Currently in BK mode!".to_string(),
+        BotEvent::BKEnded => "Received new items, resuming search...".to_string(),
+        BotEvent::IdleStarted { until: Some(until) } => format!(
+            "Outside of active hours, idling until {}...",
+            until.format("%a %H:%M")
+        ),
+        BotEvent::IdleStarted { until: None } => "Outside of active hours, idling...".to_string(),
+        BotEvent::IdleEnded => "Active hours started, resuming search...".to_string(),
+        BotEvent::CatchUpStarted { missed_checks } => {
+            format!("Catching up on {missed_checks} missed checks...")
+        }
+        BotEvent::CatchUpFinished {
+            caught_up,
+            missed_checks,
+        } => format!("Caught up on {caught_up} of {missed_checks} missed checks"),
+        BotEvent::Goal => "Goal complete!".to_string(),
+        BotEvent::Released => "Releasing items...".to_string(),
+        BotEvent::Disconnected { reason } => format!("Disconnected: {reason}"),
+        BotEvent::ServerMessage { message } => {
+            let line = render_print_json(&message, names, colored);
+            if line.trim().is_empty() {
+                return None;
+            }
+            line
+        }
+        BotEvent::ConfigReloaded {
+            needs_restart: false,
+        } => "Reloaded bot config".to_string(),
+        BotEvent::ConfigReloaded {
+            needs_restart: true,
+        } => "Reloaded bot config. Changes to [startup] apply after a restart".to_string(),
+        BotEvent::ConfigReloadFailed { error } => {
+            format!("WARNING: Could not reload bot config, keeping the old one: {error}")
+        }
+    };
+
+    Some(line)
+}
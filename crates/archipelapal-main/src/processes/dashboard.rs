@@ -0,0 +1,321 @@
+use std::{
+    collections::VecDeque,
+    io::{stdout, Stdout},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Result;
+use ratatui::{
+    backend::CrosstermBackend,
+    crossterm::{
+        event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+        execute,
+        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, LineGauge, List, Paragraph},
+    Frame, Terminal,
+};
+use tokio::{sync::broadcast::error::RecvError, task::JoinHandle, time::Instant};
+
+use crate::{
+    defs::{
+        events::BotEvent, game_state::FullGameState, lib::ArchipelaPalSlotData, logging,
+        phase::BotPhase, theme::Theme,
+    },
+    processes::console_output::{describe_event, should_ring_bell},
+    utils::get_region_from_loc_id,
+};
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How many lines of recent items and checks to keep
+const FEED_LENGTH: usize = 100;
+
+/// Draws a full-screen dashboard until the user presses `q` or Ctrl+C, which ends the task.
+/// Aborting the task also puts the terminal back the way it was.
+pub fn spawn_dashboard_task(
+    game_state: Arc<FullGameState>,
+    config: ArchipelaPalSlotData,
+) -> Result<JoinHandle<()>> {
+    let mut terminal = TerminalGuard::enter()?;
+    let mut events = game_state.subscribe();
+
+    Ok(tokio::spawn(async move {
+        let mut feed = VecDeque::with_capacity(FEED_LENGTH);
+        let mut next_check = None;
+        let mut redraw = tokio::time::interval(REDRAW_INTERVAL);
+
+        loop {
+            tokio::select! {
+                event = events.recv() => {
+                    let event = match event {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return,
+                    };
+
+                    match &event {
+                        BotEvent::WaitStarted { wait_ms } => {
+                            next_check = Some(Instant::now() + Duration::from_millis(*wait_ms));
+                        }
+                        BotEvent::LocationChecked { .. }
+                        | BotEvent::BKStarted
+                        | BotEvent::IdleStarted { .. } => next_check = None,
+                        _ => {}
+                    }
+
+                    let notifications = game_state.bot_config.read().await.notifications.clone();
+                    if should_ring_bell(&notifications, &event) {
+                        print!("\x07");
+                    }
+
+                    let names = game_state.names.read().await;
+                    if let Some(line) = describe_event(event, &names, false) {
+                        let time = chrono::Local::now().format("%H:%M:%S");
+                        if feed.len() == FEED_LENGTH {
+                            feed.pop_back();
+                        }
+                        feed.push_front(format!("{time} {line}"));
+                    }
+                }
+                _ = redraw.tick() => {
+                    if quit_requested() {
+                        return;
+                    }
+
+                    let snapshot = Snapshot::take(&game_state, &config, next_check).await;
+                    if let Err(e) = terminal.0.draw(|frame| draw(frame, &snapshot, &feed)) {
+                        log::error!("Could not draw dashboard: {e}");
+                        return;
+                    }
+                }
+            }
+        }
+    }))
+}
+
+/// Holds the terminal in raw mode on the alternate screen, and restores it when dropped
+struct TerminalGuard(Terminal<CrosstermBackend<Stdout>>);
+
+impl TerminalGuard {
+    fn enter() -> Result<Self> {
+        // Anything else written to the terminal would draw over the dashboard
+        logging::mute_stderr(true);
+        enable_raw_mode()?;
+        execute!(stdout(), EnterAlternateScreen)?;
+        Ok(Self(Terminal::new(CrosstermBackend::new(stdout()))?))
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        disable_raw_mode().ok();
+        execute!(stdout(), LeaveAlternateScreen).ok();
+        self.0.show_cursor().ok();
+        logging::mute_stderr(false);
+    }
+}
+
+fn quit_requested() -> bool {
+    while event::poll(Duration::ZERO).unwrap_or(false) {
+        let Ok(Event::Key(key)) = event::read() else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        let ctrl_c =
+            key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+        if ctrl_c || key.code == KeyCode::Char('q') {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Everything the dashboard shows, copied out so no locks are held while drawing
+struct Snapshot {
+    slot_name: String,
+    phase: BotPhase,
+    speed_modifier: f32,
+    goal_items: u16,
+    num_goal: u16,
+    exploring: u8,
+    theme: &'static Theme,
+    /// (region, checked, total, key held)
+    regions: Vec<(u8, usize, usize, bool)>,
+    keys: Vec<u8>,
+    hints: Vec<String>,
+    next_check: Option<Duration>,
+}
+
+impl Snapshot {
+    async fn take(
+        game_state: &FullGameState,
+        config: &ArchipelaPalSlotData,
+        next_check: Option<Instant>,
+    ) -> Self {
+        let player = game_state.player.read().await;
+        let map = game_state.map.read().await;
+
+        let mut keys = player.get_accessible_regions();
+        keys.sort();
+
+        let mut regions = map
+            .map
+            .iter()
+            .map(|(region, chests)| {
+                let checked = chests.iter().filter(|chest| chest.checked).count();
+                let key_held = *region == 0 || keys.contains(region);
+                (*region, checked, chests.len(), key_held)
+            })
+            .collect::<Vec<_>>();
+        regions.sort();
+
+        let names = game_state.names.read().await;
+        let mut hints = game_state
+            .source_hint_queue
+            .read()
+            .await
+            .iter()
+            .map(|hint| {
+                let location = hint.item.location;
+                let location_name = names
+                    .try_location(location, names.own_slot)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| {
+                        format!(
+                            "{}, location {location}",
+                            names
+                                .theme
+                                .region_name(get_region_from_loc_id(location as u32))
+                        )
+                    });
+                format!(
+                    "{location_name}: {} for {}",
+                    names.item(hint.item.item, hint.receiving_player),
+                    names.player(hint.receiving_player)
+                )
+            })
+            .collect::<Vec<_>>();
+        hints.sort();
+
+        Self {
+            slot_name: config.slot_name.clone(),
+            phase: *game_state.phase.read().await,
+            speed_modifier: player.speed_modifier,
+            goal_items: player.get_num_goal_items(),
+            num_goal: config.num_goal,
+            exploring: player.currently_exploring_region,
+            theme: names.theme,
+            regions,
+            keys,
+            hints,
+            next_check: next_check.map(|at| at.saturating_duration_since(Instant::now())),
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, snapshot: &Snapshot, feed: &VecDeque<String>) {
+    let [header, body, feed_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(6),
+        Constraint::Length(12),
+    ])
+    .areas(frame.area());
+    let [regions_area, side_area] =
+        Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(body);
+    let [keys_area, hints_area] =
+        Layout::vertical([Constraint::Length(3), Constraint::Min(3)]).areas(side_area);
+
+    let next_check = match (snapshot.phase, snapshot.next_check) {
+        (BotPhase::BK, _) => "BK'd".to_string(),
+        (_, Some(remaining)) => format!("{}s", remaining.as_secs()),
+        (_, None) => "-".to_string(),
+    };
+    let status = Line::from(vec![
+        format!("{} ", snapshot.slot_name).bold(),
+        format!("| {} ", snapshot.phase).into(),
+        format!("| Goal {}/{} ", snapshot.goal_items, snapshot.num_goal).into(),
+        format!("| Speed {:.2}x ", snapshot.speed_modifier).into(),
+        format!("| Next check {next_check}").into(),
+    ]);
+    frame.render_widget(
+        Paragraph::new(status).block(Block::bordered().title(" ArchipelaPal (q to quit) ")),
+        header,
+    );
+
+    draw_regions(frame, regions_area, snapshot);
+
+    let keys = match snapshot.keys.is_empty() {
+        true => "none".to_string(),
+        false => snapshot
+            .keys
+            .iter()
+            .map(|region| region.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    frame.render_widget(
+        Paragraph::new(keys).block(Block::bordered().title(" Keys ")),
+        keys_area,
+    );
+    frame.render_widget(
+        List::new(snapshot.hints.iter().map(String::as_str))
+            .block(Block::bordered().title(format!(" Hints ({}) ", snapshot.hints.len()))),
+        hints_area,
+    );
+    frame.render_widget(
+        List::new(feed.iter().map(String::as_str)).block(Block::bordered().title(" Recent ")),
+        feed_area,
+    );
+}
+
+fn draw_regions(frame: &mut Frame, area: Rect, snapshot: &Snapshot) {
+    let block = Block::bordered().title(" Regions ");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let names = snapshot
+        .regions
+        .iter()
+        .map(|(region, ..)| snapshot.theme.region_name(*region))
+        .collect::<Vec<_>>();
+    let name_width = names.iter().map(String::len).max().unwrap_or_default();
+
+    let rows = Layout::vertical(vec![Constraint::Length(1); snapshot.regions.len()]).split(inner);
+    for ((row, (region, checked, total, key_held)), name) in
+        rows.iter().zip(&snapshot.regions).zip(names)
+    {
+        let marker = if *region == snapshot.exploring {
+            ">"
+        } else {
+            " "
+        };
+        let color = match (key_held, checked == total) {
+            (_, true) => Color::Green,
+            (true, false) => Color::Yellow,
+            (false, false) => Color::DarkGray,
+        };
+        let ratio = match total {
+            0 => 1.0,
+            total => *checked as f64 / *total as f64,
+        };
+
+        frame.render_widget(
+            LineGauge::default()
+                .label(format!(
+                    "{marker}{name:<name_width$} {checked:>3}/{total:<3}"
+                ))
+                .filled_style(Style::new().fg(color))
+                .ratio(ratio),
+            *row,
+        );
+    }
+}
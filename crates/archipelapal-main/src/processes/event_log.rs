@@ -0,0 +1,21 @@
+use std::sync::Arc;
+
+use tokio::{sync::broadcast::error::RecvError, task::JoinHandle};
+
+use crate::defs::{game_state::FullGameState, logging};
+
+/// Writes every event to the log, for `--log-format json`
+pub fn spawn_event_log_task(game_state: Arc<FullGameState>) -> JoinHandle<()> {
+    let mut events = game_state.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => logging::log_event(&event),
+                Err(RecvError::Lagged(n)) => {
+                    log::warn!("Event log fell behind, skipped {n} events");
+                }
+                Err(RecvError::Closed) => return,
+            }
+        }
+    })
+}
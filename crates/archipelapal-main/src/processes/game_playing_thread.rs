@@ -1,123 +1,611 @@
-use std::{sync::Arc, time::Duration};
+use std::{pin::Pin, sync::Arc, time::Duration};
 
 use ap_rs::{
     client::ArchipelagoClientSender,
     protocol::{Get, Permission},
 };
-use rand::{thread_rng, Rng};
+use chrono::Local;
+use rand::thread_rng;
 use tokio::{
-    sync::oneshot::{self, error::TryRecvError},
+    sync::{
+        broadcast::error::RecvError,
+        mpsc,
+        oneshot::{self, error::RecvError as OneshotRecvError},
+    },
     task::JoinHandle,
+    time::{Instant, Sleep},
 };
 
 use crate::defs::{
+    control::ControlCommand,
+    events::BotEvent,
     game_state::FullGameState,
-    lib::{ArchipelaPalSlotData, GoalOneShotData},
+    lib::{ArchipelaPalSlotData, GoalOneShotData, LocationID},
+    phase::BotPhase,
+    schedule::ActiveSchedule,
 };
 
+/// How often we wake up to re-check the schedule (and for a goal) while outside active hours
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Delay between checks while catching up on missed checks
+const CATCH_UP_INTERVAL: Duration = Duration::from_millis(1500);
+
+/// The most checks we'll catch up on at once, however long we were away
+const MAX_CATCH_UP_CHECKS: u32 = 50;
+
+/// Client-side options for how the gameplay loop behaves
+#[derive(Debug, Default, Clone)]
+pub struct GameplayOptions {
+    pub active_hours: Option<ActiveSchedule>,
+    /// Make the checks we would have made while offline, in a quick burst at startup
+    pub catch_up: bool,
+    /// Let the operator pick each chest from the console
+    pub copilot: bool,
+}
+
 pub fn spawn_game_playing_task(
     game_state: Arc<FullGameState>,
     mut sender: ArchipelagoClientSender,
     config: ArchipelaPalSlotData,
     mut goal_rx: oneshot::Receiver<GoalOneShotData>,
+    mut control_rx: mpsc::Receiver<ControlCommand>,
+    options: GameplayOptions,
 ) -> JoinHandle<()> {
-    println!("Searching for items...");
+    let mut events = game_state.subscribe();
     tokio::spawn(async move {
-        let max_wait_time = config.max_wait_time;
         let min_wait_time = config.min_wait_time;
-        loop {
+        let mut idling = false;
+
+        set_phase(&game_state, &mut sender, BotPhase::Playing).await;
+
+        let is_active = options.active_hours.as_ref().map_or(true, |schedule| {
+            schedule.is_active_at(Local::now().naive_local())
+        });
+        if options.catch_up && is_active {
+            let goaled = catch_up(
+                &game_state,
+                &mut sender,
+                &config,
+                &mut goal_rx,
+                options.active_hours.as_ref(),
+            )
+            .await;
+            if goaled {
+                return;
+            }
+        }
+
+        'game: loop {
+            if *game_state.phase.read().await == BotPhase::Paused {
+                tokio::select! {
+                    goal = &mut goal_rx => {
+                        handle_goal(&game_state, &mut sender, goal).await;
+                        return;
+                    }
+                    Some(command) = control_rx.recv() => {
+                        handle_control(&game_state, &mut sender, command).await;
+                    }
+                    event = events.recv() => {
+                        if let Ok(BotEvent::Disconnected { .. }) = event {
+                            log::info!("Disconnected, shutting down gameplay thread");
+                            return;
+                        }
+                    }
+                }
+
+                continue;
+            }
+
+            let now = Local::now().naive_local();
+            let idle_time = options
+                .active_hours
+                .as_ref()
+                .and_then(|schedule| schedule.time_until_active(now));
+
+            match (idle_time, idling) {
+                (Some(_), false) => {
+                    let next_start = options
+                        .active_hours
+                        .as_ref()
+                        .and_then(|schedule| schedule.next_start_after(now));
+                    game_state.emit(BotEvent::IdleStarted { until: next_start });
+                    idling = true;
+                }
+                (None, true) => {
+                    game_state.emit(BotEvent::IdleEnded);
+                    idling = false;
+                }
+                _ => {}
+            }
+
+            if let Some(idle_time) = idle_time {
+                tokio::select! {
+                    goal = &mut goal_rx => {
+                        handle_goal(&game_state, &mut sender, goal).await;
+                        return;
+                    }
+                    _ = tokio::time::sleep(idle_time.min(IDLE_POLL_INTERVAL)) => {}
+                    Some(command) = control_rx.recv() => {
+                        handle_control(&game_state, &mut sender, command).await;
+                    }
+                    Ok(BotEvent::Disconnected { .. }) = events.recv() => {
+                        log::info!("Disconnected, shutting down gameplay thread");
+                        return;
+                    }
+                }
+
+                // Stay connected, but don't touch the game state until we're active again
+                continue;
+            }
+
+            let mut left_bk = false;
+            if *game_state.phase.read().await == BotPhase::BK {
+                // Nothing to do until something changes, so don't bother with the timer
+                tokio::select! {
+                    goal = &mut goal_rx => {
+                        handle_goal(&game_state, &mut sender, goal).await;
+                        return;
+                    }
+                    event = events.recv() => {
+                        match event {
+                            Ok(BotEvent::KeyUnlocked { region }) => {
+                                log::info!("Unlocked region {region}, leaving BK mode");
+                            }
+                            Err(RecvError::Lagged(_)) => {
+                                log::warn!("Gameplay task fell behind on events, leaving BK mode");
+                            }
+                            Ok(BotEvent::Disconnected { .. }) => {
+                                log::info!("Disconnected, shutting down gameplay thread");
+                                return;
+                            }
+                            _ => continue,
+                        }
+
+                        set_phase(&game_state, &mut sender, BotPhase::Playing).await;
+                        left_bk = true;
+                    }
+                    Some(command) = control_rx.recv() => {
+                        handle_control(&game_state, &mut sender, command).await;
+                        continue;
+                    }
+                    _ = tokio::time::sleep(IDLE_POLL_INTERVAL) => {
+                        // Re-check the schedule every so often
+                        continue;
+                    }
+                }
+            }
+
+            let mut speed_modifier = game_state.effective_speed_modifier().await;
+            let timing_model = game_state.bot_config.read().await.timing.model;
             let wait_time = {
                 // `rng` must drop out of scope before entering back into async land
                 let mut rng = thread_rng();
-                rng.gen_range(min_wait_time..=max_wait_time)
+                timing_model.sample(&mut rng, min_wait_time, config.max_wait_time)
             };
+            let mut duration = calculate_wait_duration(wait_time, speed_modifier, min_wait_time);
+            if left_bk {
+                // Whatever we were stuck on is open now, so head there like we do for new hints
+                duration = duration.min(Duration::from_secs(min_wait_time as u64));
+            }
+            log::info!("waiting for {} ms", duration.as_millis());
+            game_state.emit(BotEvent::WaitStarted {
+                wait_ms: duration.as_millis() as u64,
+            });
 
-            let duration = {
-                // Grab a read lock here, and release after finishing
-                let player = game_state.player.read().await;
-                let speed_modifier = &player.speed_modifier;
-                let wait_time = ((wait_time as f32 / speed_modifier) * 1000.0) as u64;
-                let wait_time = wait_time.max(min_wait_time as u64 * 1000);
-                log::info!("waiting for {wait_time} ms");
-                Duration::from_millis(wait_time)
-            };
-            tokio::time::sleep(duration).await;
-            match goal_rx.try_recv() {
-                Ok(data) => {
-                    // We goaled!! Send packet to server
-                    sender
-                        .status_update(ap_rs::protocol::ClientStatus::ClientGoal)
-                        .await
-                        .unwrap();
-
-                    sender.say("gg <3").await.ok();
-
-                    // Check if we need to manually release
-                    match data.room_info.permissions.release {
-                        Permission::Enabled | Permission::Goal => {
-                            log::info!("Releasing items...");
-                            println!("Releasing items...");
-                            sender.say("!release").await.unwrap();
+            let sleep = tokio::time::sleep(duration);
+            tokio::pin!(sleep);
+
+            loop {
+                tokio::select! {
+                    goal = &mut goal_rx => {
+                        handle_goal(&game_state, &mut sender, goal).await;
+                        return;
+                    }
+                    _ = &mut sleep => break,
+                    event = events.recv() => match event {
+                        Ok(BotEvent::ItemReceived { .. }) | Err(RecvError::Lagged(_)) => {
+                            // Speed boosts apply to the rest of the current wait, too
+                            let new_speed_modifier = game_state.effective_speed_modifier().await;
+                            if new_speed_modifier > speed_modifier {
+                                let remaining = rescale_wait(
+                                    sleep.as_mut(),
+                                    speed_modifier,
+                                    new_speed_modifier,
+                                );
+                                log::info!(
+                                    "Speed boosted, {} ms left to wait",
+                                    remaining.as_millis()
+                                );
+                                speed_modifier = new_speed_modifier;
+                                game_state.emit(BotEvent::WaitStarted {
+                                    wait_ms: remaining.as_millis() as u64,
+                                });
+                            }
+                        }
+                        Ok(BotEvent::Disconnected { .. }) => {
+                            log::info!("Disconnected, shutting down gameplay thread");
+                            return;
+                        }
+                        Ok(BotEvent::HintAdded { .. }) => {
+                            // Head straight for a newly hinted location, but no faster than usual
+                            let now = Instant::now();
+                            let min_deadline = now + Duration::from_secs(min_wait_time as u64);
+                            if sleep.deadline() > min_deadline {
+                                log::info!("New hints, waiting at most {min_wait_time} s");
+                                sleep.as_mut().reset(min_deadline);
+                                game_state.emit(BotEvent::WaitStarted {
+                                    wait_ms: min_wait_time as u64 * 1000,
+                                });
+                            }
                         }
-                        _ => {
-                            log::info!("I do not have to manually release!");
+                        _ => {}
+                    },
+                    Some(command) = control_rx.recv() => {
+                        handle_control(&game_state, &mut sender, command).await;
+                        if *game_state.phase.read().await == BotPhase::Paused {
+                            continue 'game;
+                        }
+
+                        let new_speed_modifier = game_state.effective_speed_modifier().await;
+                        if new_speed_modifier != speed_modifier {
+                            let remaining =
+                                rescale_wait(sleep.as_mut(), speed_modifier, new_speed_modifier);
+                            speed_modifier = new_speed_modifier;
+                            game_state.emit(BotEvent::WaitStarted {
+                                wait_ms: remaining.as_millis() as u64,
+                            });
                         }
                     }
+                }
+            }
 
-                    game_state
-                        .write_save_file()
-                        .await
-                        .inspect_err(|e| log::error!("Error writing save file on goal: {e}"))
-                        .ok();
+            let mut picked_location = None;
+            let choices = match options.copilot {
+                true => game_state.reachable_chests().await,
+                false => vec![],
+            };
+            if !choices.is_empty() {
+                let timeout_secs = game_state.bot_config.read().await.copilot.timeout_secs;
+                game_state.emit(BotEvent::ChestChoiceOffered {
+                    locations: choices.clone(),
+                    timeout_secs,
+                });
 
-                    // End the thread :)
-                    log::info!("Shutting down gameplay thread");
-                    return;
-                }
-                Err(e) => match e {
-                    TryRecvError::Empty => {
-                        // All good, we just haven't goaled yet.
-                    }
-                    TryRecvError::Closed => {
-                        panic!("GOAL oneshot is poisoned!");
+                let timeout = tokio::time::sleep(Duration::from_secs(timeout_secs));
+                tokio::pin!(timeout);
+                // Stop waiting for a pick if our active hours end in the meantime
+                let mut schedule_check = tokio::time::interval_at(
+                    Instant::now() + IDLE_POLL_INTERVAL,
+                    IDLE_POLL_INTERVAL,
+                );
+
+                while picked_location.is_none() {
+                    tokio::select! {
+                        goal = &mut goal_rx => {
+                            handle_goal(&game_state, &mut sender, goal).await;
+                            return;
+                        }
+                        _ = &mut timeout => {
+                            log::info!("Nobody picked a chest, choosing one myself");
+                            break;
+                        }
+                        _ = schedule_check.tick(), if options.active_hours.is_some() => {
+                            let now = Local::now().naive_local();
+                            if options.active_hours.as_ref().is_some_and(|s| !s.is_active_at(now)) {
+                                log::info!("Active hours are over, no longer waiting for a pick");
+                                continue 'game;
+                            }
+                        }
+                        event = events.recv() => {
+                            if let Ok(BotEvent::Disconnected { .. }) = event {
+                                log::info!("Disconnected, shutting down gameplay thread");
+                                return;
+                            }
+                        }
+                        Some(command) = control_rx.recv() => match command {
+                            ControlCommand::PickChest(loc_id) => {
+                                if choices.contains(&loc_id) && game_state.check_location(loc_id).await {
+                                    picked_location = Some(loc_id);
+                                } else {
+                                    log::warn!("Can't check location {loc_id:x} right now");
+                                }
+                            }
+                            command => {
+                                handle_control(&game_state, &mut sender, command).await;
+                                if *game_state.phase.read().await == BotPhase::Paused {
+                                    continue 'game;
+                                }
+                            }
+                        },
                     }
-                },
-            };
+                }
+            }
 
-            // A write lock is grabbed here, and ofc released after finishing
-            let location_checked = game_state.tick_game_state().await;
+            let location_checked = match picked_location {
+                Some(loc_id) => Some(loc_id),
+                // A write lock is grabbed here, and ofc released after finishing
+                None => game_state.tick_game_state().await,
+            };
 
             match location_checked {
                 None => {
                     // BK'd!
                     log::warn!("I'm BK'd!!!");
-                    println!("Currently in BK mode!");
+                    set_phase(&game_state, &mut sender, BotPhase::BK).await;
+                    handle_bk(&game_state, &mut sender).await;
                 }
                 Some(loc_id) => {
                     // Found an item!
-                    println!("Checked location ID: {loc_id} (Hex: {loc_id:x})");
-                    let loc_id = loc_id as i32;
-                    match sender.location_checks(vec![loc_id]).await {
-                        Ok(_) => {
-                            // Remove from hint queue
-                            let mut source_hint_queue = game_state.source_hint_queue.write().await;
-                            source_hint_queue.retain(|hint| hint.item.location != loc_id);
-                        }
-                        Err(e) => {
-                            log::error!("{e}");
-                        }
-                    };
+                    send_check(&game_state, &mut sender, loc_id).await;
                 }
             }
 
-            let hint_get_key = game_state.make_hints_get_key(game_state.slot_id);
-
-            sender
-                .send(ap_rs::protocol::ClientMessage::Get(Get {
-                    keys: vec![hint_get_key],
-                }))
-                .await
-                .ok();
+            request_hints(&game_state, &mut sender).await;
         }
     })
 }
+
+async fn set_phase(
+    game_state: &FullGameState,
+    sender: &mut ArchipelagoClientSender,
+    next: BotPhase,
+) {
+    game_state
+        .set_phase(sender, next)
+        .await
+        .inspect_err(|e| log::error!("Could not change phase: {e}"))
+        .ok();
+}
+
+async fn handle_control(
+    game_state: &FullGameState,
+    sender: &mut ArchipelagoClientSender,
+    command: ControlCommand,
+) {
+    log::info!("Operator command: {command:?}");
+    match command {
+        ControlCommand::Pause => set_phase(game_state, sender, BotPhase::Paused).await,
+        ControlCommand::Resume => {
+            if *game_state.phase.read().await == BotPhase::Paused {
+                set_phase(game_state, sender, BotPhase::Playing).await;
+            }
+        }
+        ControlCommand::SetSpeedOverride(speed_override) => {
+            *game_state.speed_override.write().await = speed_override;
+        }
+        ControlCommand::Say(message) => {
+            if let Err(e) = sender.say(&message).await {
+                log::error!("Could not send message: {e}");
+            }
+        }
+        ControlCommand::PickChest(loc_id) => {
+            log::warn!("Not offering any chests right now, ignoring pick of {loc_id:x}");
+        }
+    }
+}
+
+/// Stretches or shrinks what's left of `sleep` for a new speed modifier. Returns the new remaining
+/// wait.
+fn rescale_wait(sleep: Pin<&mut Sleep>, from: f32, to: f32) -> Duration {
+    let now = Instant::now();
+    let remaining = sleep.deadline().saturating_duration_since(now);
+    let remaining = remaining.mul_f32(from / to);
+    sleep.reset(now + remaining);
+
+    remaining
+}
+
+pub(crate) fn calculate_wait_duration(
+    wait_time: u16,
+    speed_modifier: f32,
+    min_wait_time: u16,
+) -> Duration {
+    let wait_time = ((wait_time as f32 / speed_modifier) * 1000.0) as u64;
+    let wait_time = wait_time.max(min_wait_time as u64 * 1000);
+    Duration::from_millis(wait_time)
+}
+
+async fn handle_goal(
+    game_state: &FullGameState,
+    sender: &mut ArchipelagoClientSender,
+    goal: Result<GoalOneShotData, OneshotRecvError>,
+) {
+    let Ok(data) = goal else {
+        panic!("GOAL oneshot is poisoned!");
+    };
+
+    // We goaled!! Send packet to server
+    set_phase(game_state, sender, BotPhase::Goaled).await;
+
+    let goal_message = game_state.bot_config.read().await.chat.goal_message.clone();
+    if let Some(message) = goal_message {
+        sender.say(&message).await.ok();
+    }
+
+    // Check if we need to manually release
+    match data.room_info.permissions.release {
+        Permission::Enabled | Permission::Goal => {
+            log::info!("Releasing items...");
+            sender.say("!release").await.unwrap();
+            set_phase(game_state, sender, BotPhase::Released).await;
+        }
+        _ => {
+            log::info!("I do not have to manually release!");
+        }
+    }
+
+    // End the thread :)
+    log::info!("Shutting down gameplay thread");
+}
+
+/// Says the BK message, and spends a hint on the next key if we're allowed to
+async fn handle_bk(game_state: &FullGameState, sender: &mut ArchipelagoClientSender) {
+    let bot_config = game_state.bot_config.read().await.clone();
+    let theme = game_state.names.read().await.theme;
+
+    let held_keys = game_state.player.read().await.get_accessible_regions();
+    let next_locked_region = game_state
+        .map
+        .read()
+        .await
+        .map
+        .keys()
+        .filter(|region| **region != 0 && !held_keys.contains(region))
+        .min()
+        .copied();
+
+    if let Some(message) = &bot_config.chat.bk_message {
+        let region = match next_locked_region {
+            Some(region) => theme.describe_region(region),
+            None => "the next region".to_string(),
+        };
+        sender.say(&message.replace("{region}", &region)).await.ok();
+    }
+
+    if !bot_config.hints.spend_when_bk {
+        return;
+    }
+
+    let Some(region) = next_locked_region else {
+        return;
+    };
+    let key_name = game_state.names.read().await.key_item(region);
+    match key_name {
+        Some(key_name) => {
+            log::info!("Hinting for {key_name}");
+            sender.say(&format!("!hint {key_name}")).await.ok();
+        }
+        None => log::warn!("No DataPackage name for the key to Region {region}, not hinting"),
+    }
+}
+
+/// Makes the checks we would have made while we were active but offline, at our current speed.
+/// This stops early if we BK, since keys from our own checks may not have arrived yet. Returns
+/// true if we goaled along the way.
+async fn catch_up(
+    game_state: &FullGameState,
+    sender: &mut ArchipelagoClientSender,
+    config: &ArchipelaPalSlotData,
+    goal_rx: &mut oneshot::Receiver<GoalOneShotData>,
+    active_hours: Option<&ActiveSchedule>,
+) -> bool {
+    let missed_checks = game_state
+        .missed_checks_since_last_active(config, active_hours)
+        .await;
+    if missed_checks == 0 {
+        return false;
+    }
+    if missed_checks > MAX_CATCH_UP_CHECKS {
+        log::info!("Missed {missed_checks} checks, only catching up on {MAX_CATCH_UP_CHECKS}");
+    }
+    let missed_checks = missed_checks.min(MAX_CATCH_UP_CHECKS);
+
+    game_state.emit(BotEvent::CatchUpStarted { missed_checks });
+    log::info!("Catching up on {missed_checks} missed checks");
+
+    let mut caught_up = 0;
+    for _ in 0..missed_checks {
+        let Some(loc_id) = game_state.tick_game_state().await else {
+            log::warn!("BK'd while catching up, resuming normal pace");
+            set_phase(game_state, sender, BotPhase::BK).await;
+            handle_bk(game_state, sender).await;
+            break;
+        };
+
+        send_check(game_state, sender, loc_id).await;
+        caught_up += 1;
+
+        tokio::select! {
+            goal = &mut *goal_rx => {
+                handle_goal(game_state, sender, goal).await;
+                return true;
+            }
+            _ = tokio::time::sleep(CATCH_UP_INTERVAL) => {}
+        }
+    }
+
+    request_hints(game_state, sender).await;
+    game_state.emit(BotEvent::CatchUpFinished {
+        caught_up,
+        missed_checks,
+    });
+
+    false
+}
+
+async fn send_check(
+    game_state: &FullGameState,
+    sender: &mut ArchipelagoClientSender,
+    loc_id: LocationID,
+) {
+    match sender.location_checks(vec![loc_id as i32]).await {
+        Ok(_) => {
+            game_state.emit(BotEvent::LocationChecked {
+                location_id: loc_id,
+            });
+
+            // Remove from hint queue
+            let mut source_hint_queue = game_state.source_hint_queue.write().await;
+            let hint_count = source_hint_queue.len();
+            source_hint_queue.retain(|hint| hint.item.location != loc_id as i32);
+            if source_hint_queue.len() != hint_count {
+                game_state.emit(BotEvent::HintRemoved {
+                    location: loc_id as i32,
+                });
+            }
+        }
+        Err(e) => {
+            // Checking a location twice is harmless, so leave it to be checked again later
+            // rather than have the save say it's done when the server may never have heard
+            log::error!("Could not send check for location {loc_id:x}, will retry it: {e}");
+            if game_state.uncheck_location(loc_id).await {
+                game_state.emit(BotEvent::LocationUnchecked {
+                    location_id: loc_id,
+                });
+            }
+        }
+    };
+}
+
+async fn request_hints(game_state: &FullGameState, sender: &mut ArchipelagoClientSender) {
+    let hint_get_key = game_state.make_hints_get_key(game_state.slot_id);
+
+    sender
+        .send(ap_rs::protocol::ClientMessage::Get(Get {
+            keys: vec![hint_get_key],
+        }))
+        .await
+        .ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calculate_wait_duration() {
+        assert_eq!(calculate_wait_duration(10, 1.0, 1), Duration::from_secs(10));
+        assert_eq!(calculate_wait_duration(10, 2.0, 1), Duration::from_secs(5));
+        assert_eq!(calculate_wait_duration(10, 0.5, 1), Duration::from_secs(20));
+        // Never faster than the minimum, however fast we are
+        assert_eq!(
+            calculate_wait_duration(10, 100.0, 3),
+            Duration::from_secs(3)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rescale_wait() {
+        let sleep = tokio::time::sleep(Duration::from_secs(100));
+        tokio::pin!(sleep);
+
+        let remaining = rescale_wait(sleep.as_mut(), 1.0, 2.0);
+        assert!(remaining > Duration::from_secs(49) && remaining <= Duration::from_secs(50));
+        let until_deadline = sleep.deadline().saturating_duration_since(Instant::now());
+        assert!(until_deadline <= remaining);
+
+        let remaining = rescale_wait(sleep.as_mut(), 2.0, 1.0);
+        assert!(remaining > Duration::from_secs(99) && remaining <= Duration::from_secs(100));
+    }
+}
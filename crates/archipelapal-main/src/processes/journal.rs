@@ -0,0 +1,138 @@
+use std::{path::Path, process, sync::Arc};
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::broadcast::error::RecvError,
+    task::JoinHandle,
+};
+
+use crate::defs::{events::BotEvent, game_state::FullGameState};
+
+#[derive(Serialize)]
+struct JournalEntry<'a> {
+    /// Local time, like the log
+    timestamp: DateTime<Local>,
+    #[serde(flatten)]
+    record: Record<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Record<'a> {
+    Event(&'a BotEvent),
+    Note(JournalNote),
+}
+
+/// Entries about the journal itself, rather than the bot
+#[derive(Serialize)]
+#[serde(tag = "event")]
+enum JournalNote {
+    /// Every run appends to the same journal, so this marks where each one begins
+    RunStarted { pid: u32, version: &'static str },
+    /// We fell behind the event bus, and this many events are missing from the journal
+    Lagged { skipped: u64 },
+}
+
+/// Appends the bot's events to a JSONL journal next to the save file, so a run can be
+/// reconstructed after the fact
+pub fn spawn_journal_task(game_state: Arc<FullGameState>) -> JoinHandle<()> {
+    let mut events = game_state.subscribe();
+    let path = FullGameState::make_journal_file_name(
+        &game_state.seed_name,
+        game_state.team,
+        game_state.slot_id,
+    );
+    tokio::spawn(async move {
+        let mut file = match open_journal(&path).await {
+            Ok(file) => file,
+            Err(e) => {
+                log::error!("Unable to open journal {}: {e}", path.display());
+                return;
+            }
+        };
+
+        let run_started = JournalNote::RunStarted {
+            pid: process::id(),
+            version: env!("CARGO_PKG_VERSION"),
+        };
+        append_entry(&mut file, Record::Note(run_started))
+            .await
+            .inspect_err(|e| log::error!("Unable to write to journal: {e}"))
+            .ok();
+
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(n)) => {
+                    log::warn!("Journal fell behind, skipped {n} events");
+                    append_entry(&mut file, Record::Note(JournalNote::Lagged { skipped: n }))
+                        .await
+                        .inspect_err(|e| log::error!("Unable to write to journal: {e}"))
+                        .ok();
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            };
+            if !event.is_journaled() {
+                continue;
+            }
+
+            append_entry(&mut file, Record::Event(&event))
+                .await
+                .inspect_err(|e| log::error!("Unable to write to journal: {e}"))
+                .ok();
+        }
+    })
+}
+
+async fn open_journal(path: &Path) -> Result<File> {
+    Ok(OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?)
+}
+
+async fn append_entry(file: &mut File, record: Record<'_>) -> Result<()> {
+    let mut line = to_line(record)?;
+    line.push('\n');
+
+    file.write_all(line.as_bytes()).await?;
+    file.flush().await?;
+
+    Ok(())
+}
+
+fn to_line(record: Record<'_>) -> Result<String> {
+    let entry = JournalEntry {
+        timestamp: Local::now(),
+        record,
+    };
+    Ok(serde_json::to_string(&entry)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries() {
+        let line = to_line(Record::Note(JournalNote::Lagged { skipped: 3 })).unwrap();
+        let entry: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(entry["event"], "Lagged");
+        assert_eq!(entry["skipped"], 3);
+        // Local time, with its offset
+        let timestamp = entry["timestamp"].as_str().unwrap();
+        assert!(DateTime::parse_from_rfc3339(timestamp).is_ok());
+
+        let event = BotEvent::KeyUnlocked { region: 2 };
+        let line = to_line(Record::Event(&event)).unwrap();
+        let entry: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(entry["event"], "KeyUnlocked");
+        assert_eq!(entry["region"], 2);
+    }
+}
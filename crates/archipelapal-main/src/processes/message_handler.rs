@@ -1,7 +1,7 @@
 use anyhow::Result;
 use ap_rs::{
     client::ArchipelagoClientReceiver,
-    protocol::{ClientStatus, Hint, HintData, ServerMessage},
+    protocol::{ClientStatus, Hint, HintData, NetworkItem, ReceivedItems, ServerMessage},
 };
 use std::{
     collections::{HashMap, HashSet},
@@ -10,8 +10,12 @@ use std::{
 use tokio::{sync::oneshot, task::JoinHandle};
 
 use crate::defs::{
+    data_package::{self, GameNames},
+    events::BotEvent,
     game_state::FullGameState,
-    lib::{ArchipelaPalSlotData, GoalData, GoalOneShotData},
+    lib::{ArchipelaPalSlotData, GoalData, GoalOneShotData, ItemID},
+    phase::BotPhase,
+    player::Player,
 };
 
 pub fn spawn_ap_server_task(
@@ -20,7 +24,6 @@ pub fn spawn_ap_server_task(
     config: ArchipelaPalSlotData,
     goal_tx: oneshot::Sender<GoalOneShotData>,
 ) -> JoinHandle<()> {
-    println!("Now listening for AP server messages");
     tokio::spawn(async move {
         loop {
             let msg = client.recv().await;
@@ -30,47 +33,39 @@ pub fn spawn_ap_server_task(
                     match msg {
                         ServerMessage::ReceivedItems(items) => {
                             let mut player = game_state.player.write().await;
-                            let last_idx = game_state.last_checked_idx.read().await;
-                            if items.index == 0 {
-                                // What we receive is the ENTIRE inventory when idx == 0
-                                // Set the player's state and return
-                                let new_player_inventory = items.items.into_iter().fold(
-                                    HashMap::new(),
-                                    |mut acc, curr| {
-                                        if curr.item < 0 {
-                                            return acc;
-                                        }
-                                        let id = curr.item as u32;
-                                        let amt = acc.entry(id).or_insert(0);
-                                        *amt += 1;
-
-                                        acc
-                                    },
-                                );
-
-                                player.inventory = new_player_inventory;
-                            } else if items.index > *last_idx {
-                                for item in items.items.iter() {
-                                    let id = item.item;
-
-                                    if id < 0 {
-                                        // Special AP item. don't use
-                                        continue;
-                                    }
-                                    let id = id as u32;
+                            let old_regions = player.get_accessible_regions();
+                            let mut last_idx = game_state.last_checked_idx.write().await;
+                            let old_idx = *last_idx;
+                            let new_items =
+                                apply_received_items(&mut player, &mut last_idx, &items);
+                            if new_items.is_empty() && *last_idx != old_idx {
+                                game_state.emit(BotEvent::ItemIndexSynced { index: *last_idx });
+                            }
+                            drop(last_idx);
 
-                                    // Append to inventory for now...
-                                    let entry = player.inventory.entry(id).or_insert(0);
-                                    *entry += 1;
-                                }
+                            let old_speed_modifier = player.speed_modifier;
+                            player.set_speed_modifier();
+                            if player.speed_modifier != old_speed_modifier {
+                                game_state.emit(BotEvent::SpeedChanged {
+                                    from: old_speed_modifier,
+                                    to: player.speed_modifier,
+                                });
+                            }
 
-                                // Drop read lock to get a write
-                                drop(last_idx);
+                            for (index, item) in new_items {
+                                game_state.emit(BotEvent::ItemReceived {
+                                    item_id: item.item as ItemID,
+                                    sender: item.player,
+                                    location: item.location,
+                                    index,
+                                });
+                            }
 
-                                let mut last_idx_write = game_state.last_checked_idx.write().await;
-                                *last_idx_write = items.index;
+                            for region in player.get_accessible_regions() {
+                                if !old_regions.contains(&region) {
+                                    game_state.emit(BotEvent::KeyUnlocked { region });
+                                }
                             }
-                            player.set_speed_modifier();
 
                             let player = player.downgrade();
                             // Quick goal check
@@ -87,12 +82,6 @@ pub fn spawn_ap_server_task(
                                 log::info!("Server listening thread shutting down");
                                 return;
                             }
-
-                            game_state
-                                .write_save_file()
-                                .await
-                                .inspect_err(|e| log::error!("Unable to write save file: {e}"))
-                                .ok();
                         }
                         ServerMessage::Retrieved(retrieved) => {
                             for (key, val) in retrieved.keys.iter() {
@@ -156,11 +145,23 @@ pub fn spawn_ap_server_task(
 
                                     let mut source_hint_queue =
                                         game_state.source_hint_queue.write().await;
+                                    for hint in hints_parsed.difference(&source_hint_queue) {
+                                        game_state.emit(BotEvent::HintAdded { hint: hint.clone() });
+                                    }
+                                    for hint in source_hint_queue.difference(&hints_parsed) {
+                                        game_state.emit(BotEvent::HintRemoved {
+                                            location: hint.item.location,
+                                        });
+                                    }
                                     *source_hint_queue = hints_parsed;
                                 }
                             }
                         }
                         ServerMessage::PrintJSON(print_json) => {
+                            game_state.emit(BotEvent::ServerMessage {
+                                message: print_json.clone(),
+                            });
+
                             if print_json.found.is_none() {
                                 // Not a hint
                                 continue;
@@ -175,7 +176,24 @@ pub fn spawn_ap_server_task(
                                 // This hint is an item that comes from us
                                 let mut source_hint_queue =
                                     game_state.source_hint_queue.write().await;
-                                source_hint_queue.insert(hint);
+                                if source_hint_queue.insert(hint.clone()) {
+                                    game_state.emit(BotEvent::HintAdded { hint });
+                                }
+                            }
+                        }
+                        ServerMessage::DataPackage(data_package) => {
+                            let mut names = game_state.names.write().await;
+                            for (game, data) in data_package.data.games {
+                                // Cache under the checksum we asked for, and only if this is that data
+                                let checksum = client.room_info().datapackage_checksums.get(&game);
+                                if let Some(checksum) = checksum.filter(|c| **c == data.checksum) {
+                                    if let Err(e) = data_package::save_cached(checksum, &data) {
+                                        log::warn!(
+                                            "Could not cache the DataPackage for {game}: {e}"
+                                        );
+                                    }
+                                }
+                                names.add_game(game, GameNames::from(&data));
                             }
                         }
                         _ => {
@@ -196,6 +214,14 @@ pub fn spawn_ap_server_task(
                             continue;
                         }
                         _ => {
+                            game_state
+                                .transition_to(BotPhase::Disconnected)
+                                .await
+                                .inspect_err(|e| log::error!("Could not change phase: {e}"))
+                                .ok();
+                            game_state.emit(BotEvent::Disconnected {
+                                reason: e.to_string(),
+                            });
                             panic!("{e}");
                         }
                     }
@@ -204,3 +230,118 @@ pub fn spawn_ap_server_task(
         }
     })
 }
+
+/// Adds a `ReceivedItems` to our inventory, and moves `last_idx` up to match. Returns the
+/// (index, item) pairs we hadn't seen before.
+fn apply_received_items<'a>(
+    player: &mut Player,
+    last_idx: &mut i32,
+    items: &'a ReceivedItems,
+) -> Vec<(i32, &'a NetworkItem)> {
+    let mut new_items = vec![];
+
+    if items.index == 0 {
+        // What we receive is the ENTIRE inventory when idx == 0
+        let mut new_player_inventory = HashMap::new();
+        for (i, item) in items.items.iter().enumerate() {
+            if item.item < 0 {
+                continue;
+            }
+            let id = item.item as u32;
+            let amt = new_player_inventory.entry(id).or_insert(0);
+            *amt += 1;
+
+            // Anything past what we already had is new to us
+            if *amt > player.inventory.get(&id).copied().unwrap_or(0) {
+                new_items.push((i as i32, item));
+            }
+        }
+
+        player.inventory = new_player_inventory;
+
+        // Without a save (or with a stale one), this is how we learn where we're up to
+        let synced_idx = items.items.len() as i32 - 1;
+        *last_idx = (*last_idx).max(synced_idx);
+    } else if items.index > *last_idx {
+        for (i, item) in items.items.iter().enumerate() {
+            let id = item.item;
+
+            if id < 0 {
+                // Special AP item. don't use
+                continue;
+            }
+            let id = id as u32;
+
+            // Append to inventory for now...
+            let entry = player.inventory.entry(id).or_insert(0);
+            *entry += 1;
+            new_items.push((items.index + i as i32, item));
+        }
+
+        *last_idx = items.index;
+    }
+
+    new_items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn received(index: i32, item_ids: &[i32]) -> ReceivedItems {
+        ReceivedItems {
+            index,
+            items: item_ids
+                .iter()
+                .map(|&item| NetworkItem {
+                    item,
+                    location: 0,
+                    player: 1,
+                    flags: 0,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_full_sync_longer_than_inventory() {
+        let mut player = Player::default();
+        player.inventory.insert(1, 1);
+        let mut last_idx = 0;
+
+        let items = received(0, &[1, 2, 1]);
+        let new_items = apply_received_items(&mut player, &mut last_idx, &items);
+
+        let new_indices = new_items.iter().map(|(idx, _)| *idx).collect::<Vec<_>>();
+        assert_eq!(new_indices, vec![1, 2]);
+        assert_eq!(player.inventory, HashMap::from([(1, 2), (2, 1)]));
+        assert_eq!(last_idx, 2);
+    }
+
+    #[test]
+    fn test_full_sync_shorter_than_inventory() {
+        let mut player = Player::default();
+        player.inventory.insert(1, 3);
+        player.inventory.insert(2, 1);
+        let mut last_idx = 5;
+
+        let items = received(0, &[1, 1]);
+        let new_items = apply_received_items(&mut player, &mut last_idx, &items);
+
+        // The server's list wins, but never moves us backwards
+        assert!(new_items.is_empty());
+        assert_eq!(player.inventory, HashMap::from([(1, 2)]));
+        assert_eq!(last_idx, 5);
+    }
+
+    #[test]
+    fn test_stale_items_ignored() {
+        let mut player = Player::default();
+        let mut last_idx = 4;
+
+        let items = received(3, &[1]);
+        assert!(apply_received_items(&mut player, &mut last_idx, &items).is_empty());
+        assert!(player.inventory.is_empty());
+        assert_eq!(last_idx, 4);
+    }
+}
@@ -1,2 +1,9 @@
+pub mod config_watcher;
+pub mod console_output;
+pub mod dashboard;
+pub mod event_log;
 pub mod game_playing_thread;
+pub mod journal;
 pub mod message_handler;
+pub mod operator_console;
+pub mod save_writer;
@@ -0,0 +1,304 @@
+use std::sync::Arc;
+
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{
+    defs::{
+        control::ControlCommand, events::BotEvent, game_state::FullGameState, input,
+        lib::LocationID, phase::BotPhase,
+    },
+    utils::get_region_from_loc_id,
+};
+
+const HELP: &str = "\
+Commands:
+  status             Show what the bot is up to
+  pause              Stop making checks
+  resume             Carry on after a pause
+  speed <multiplier> Multiply the bot's speed, e.g. `speed 0.5` for half speed
+  say <message>      Say something in the room
+  !<command>         Send a server command, e.g. `!hint`
+  <number>           Pick a chest from the list, in co-pilot mode
+  quit               Save and disconnect
+  help               Show this list";
+
+/// Reads commands from stdin while the bot runs. The task ends when the operator quits.
+pub fn spawn_operator_console_task(
+    game_state: Arc<FullGameState>,
+    control_tx: mpsc::Sender<ControlCommand>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        println!("Type `help` for a list of commands");
+        let mut events = game_state.subscribe();
+        // The chests the bot is waiting on us to pick from, if any
+        let mut offer: Vec<LocationID> = vec![];
+
+        loop {
+            let line = tokio::select! {
+                line = input::next_line() => match line {
+                    Some(line) => line,
+                    None => {
+                        // Nobody is there to type anything, but that's no reason to stop playing
+                        log::info!("Stdin closed, operator console disabled");
+                        std::future::pending::<()>().await;
+                        return;
+                    }
+                },
+                event = events.recv() => {
+                    match event {
+                        Ok(BotEvent::ChestChoiceOffered { locations, timeout_secs }) => {
+                            print_offer(&game_state, &locations, timeout_secs).await;
+                            offer = locations;
+                        }
+                        Ok(
+                            BotEvent::LocationChecked { .. }
+                            | BotEvent::BKStarted
+                            | BotEvent::PhaseChanged {
+                                to: BotPhase::Paused | BotPhase::Disconnected,
+                                ..
+                            },
+                        ) => offer.clear(),
+                        _ => {}
+                    }
+                    continue;
+                }
+            };
+
+            let allow_speed_up = game_state.bot_config.read().await.console.allow_speed_up;
+            let control = match parse_command(&line, &offer, allow_speed_up) {
+                Ok(ConsoleCommand::Nothing) => continue,
+                Ok(ConsoleCommand::Help) => {
+                    println!("{HELP}");
+                    continue;
+                }
+                Ok(ConsoleCommand::Status) => {
+                    print_status(&game_state).await;
+                    continue;
+                }
+                Ok(ConsoleCommand::Quit) => {
+                    println!("Saving and disconnecting...");
+                    return;
+                }
+                Ok(ConsoleCommand::Control(control)) => control,
+                Err(message) => {
+                    println!("{message}");
+                    continue;
+                }
+            };
+            if let ControlCommand::SetSpeedOverride(speed_override) = control {
+                println!("Speed override set to {speed_override}x");
+            }
+
+            if control_tx.send(control).await.is_err() {
+                println!("The game has stopped, only `status` and `quit` work now");
+            }
+        }
+    })
+}
+
+async fn print_offer(game_state: &FullGameState, locations: &[LocationID], timeout_secs: u64) {
+    let source_hint_queue = game_state.source_hint_queue.read().await;
+    let names = game_state.names.read().await;
+
+    println!("Pick the next chest, or I'll choose one in {timeout_secs} s:");
+    for (idx, loc_id) in locations.iter().enumerate() {
+        let hinted = source_hint_queue
+            .iter()
+            .any(|hint| hint.item.location == *loc_id as i32);
+        let name = names
+            .try_location(*loc_id as i32, names.own_slot)
+            .map(str::to_string)
+            .unwrap_or_else(|| {
+                format!(
+                    "{}, location {loc_id:x}",
+                    names.theme.region_name(get_region_from_loc_id(*loc_id))
+                )
+            });
+        println!(
+            "  {}) {name}{}",
+            idx + 1,
+            if hinted { " (hinted)" } else { "" }
+        );
+    }
+}
+
+/// What the operator asked for with a line of input
+#[derive(Debug, PartialEq)]
+enum ConsoleCommand {
+    /// A blank line
+    Nothing,
+    Help,
+    Status,
+    Quit,
+    /// Something for the gameplay loop
+    Control(ControlCommand),
+}
+
+/// Makes sense of a line typed into the console. `offer` is the chests on offer in co-pilot mode,
+/// in the order they were listed. Errors are messages for the operator.
+fn parse_command(
+    line: &str,
+    offer: &[LocationID],
+    allow_speed_up: bool,
+) -> Result<ConsoleCommand, String> {
+    let line = line.trim();
+    let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+    let arg = arg.trim();
+
+    if let Ok(choice) = command.parse::<usize>() {
+        let Some(loc_id) = choice.checked_sub(1).and_then(|idx| offer.get(idx)) else {
+            return Err(match offer.is_empty() {
+                true => "Nothing to pick right now".to_string(),
+                false => format!("Pick a number from 1 to {}", offer.len()),
+            });
+        };
+        return Ok(ConsoleCommand::Control(ControlCommand::PickChest(*loc_id)));
+    }
+
+    let control = match command {
+        "" => return Ok(ConsoleCommand::Nothing),
+        "help" => return Ok(ConsoleCommand::Help),
+        "status" => return Ok(ConsoleCommand::Status),
+        "quit" | "exit" => return Ok(ConsoleCommand::Quit),
+        "pause" => ControlCommand::Pause,
+        "resume" => ControlCommand::Resume,
+        "speed" => ControlCommand::SetSpeedOverride(parse_speed(arg, allow_speed_up)?),
+        "say" if !arg.is_empty() => ControlCommand::Say(arg.to_string()),
+        _ if command.starts_with('!') => ControlCommand::Say(line.to_string()),
+        _ => {
+            return Err(format!(
+                "Unknown command `{line}`, type `help` for a list of commands"
+            ))
+        }
+    };
+
+    Ok(ConsoleCommand::Control(control))
+}
+
+fn parse_speed(arg: &str, allow_speed_up: bool) -> Result<f32, String> {
+    let speed_override = arg
+        .parse::<f32>()
+        .ok()
+        .filter(|speed| speed.is_finite() && *speed > 0.0)
+        .ok_or_else(|| format!("`{arg}` is not a speed, try something like `speed 0.5`"))?;
+
+    if speed_override > 1.0 && !allow_speed_up {
+        return Err(
+            "Speeding up isn't allowed. Set `allow_speed_up` under [console] in the bot config \
+            if the room's host is fine with it"
+                .to_string(),
+        );
+    }
+
+    Ok(speed_override)
+}
+
+async fn print_status(game_state: &FullGameState) {
+    let phase = *game_state.phase.read().await;
+    let player = game_state.player.read().await;
+    let map = game_state.map.read().await;
+    let speed_override = *game_state.speed_override.read().await;
+
+    let chests = map.map.values().flatten();
+    let total = chests.clone().count();
+    let checked = chests.filter(|chest| chest.checked).count();
+    let num_goal = game_state
+        .num_goal
+        .map(|num_goal| num_goal.to_string())
+        .unwrap_or_else(|| "?".to_string());
+    let mut keys = player.get_accessible_regions();
+    keys.sort();
+
+    println!("Phase: {phase}");
+    println!("Checked: {checked}/{total}");
+    println!("Goal items: {}/{num_goal}", player.get_num_goal_items());
+    if speed_override == 1.0 {
+        println!("Speed modifier: {:.2}x", player.speed_modifier);
+    } else {
+        println!(
+            "Speed modifier: {:.2}x, with a {speed_override}x override",
+            player.speed_modifier
+        );
+    }
+    let theme = game_state.names.read().await.theme;
+    println!(
+        "Exploring {}",
+        theme.describe_region(player.currently_exploring_region)
+    );
+    println!("Keys: {keys:?}");
+    println!(
+        "Pending hints: {}",
+        game_state.source_hint_queue.read().await.len()
+    );
+    if phase == BotPhase::Paused {
+        println!("Type `resume` to carry on");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn control(line: &str, allow_speed_up: bool) -> Option<ControlCommand> {
+        match parse_command(line, &[0x03010001, 0x03010002], allow_speed_up) {
+            Ok(ConsoleCommand::Control(control)) => Some(control),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("  ", &[], false), Ok(ConsoleCommand::Nothing));
+        assert_eq!(
+            parse_command("status", &[], false),
+            Ok(ConsoleCommand::Status)
+        );
+        assert_eq!(parse_command("exit", &[], false), Ok(ConsoleCommand::Quit));
+        assert_eq!(control("pause", false), Some(ControlCommand::Pause));
+        assert!(parse_command("dance", &[], false).is_err());
+
+        // Chat and server commands are passed through as they are
+        assert_eq!(
+            control("say  hello there ", false),
+            Some(ControlCommand::Say("hello there".to_string()))
+        );
+        assert!(control("say", false).is_none());
+        assert_eq!(
+            control("!hint Key 3", false),
+            Some(ControlCommand::Say("!hint Key 3".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_speed() {
+        assert_eq!(
+            control("speed 0.5", false),
+            Some(ControlCommand::SetSpeedOverride(0.5))
+        );
+        assert!(control("speed 2", false).is_none());
+        assert_eq!(
+            control("speed 2", true),
+            Some(ControlCommand::SetSpeedOverride(2.0))
+        );
+        for speed in ["", "fast", "0", "-1", "NaN", "inf"] {
+            assert!(control(&format!("speed {speed}"), true).is_none());
+        }
+    }
+
+    #[test]
+    fn test_parse_pick() {
+        assert_eq!(
+            control("2", false),
+            Some(ControlCommand::PickChest(0x03010002))
+        );
+        assert_eq!(
+            parse_command("3", &[0x03010001, 0x03010002], false),
+            Err("Pick a number from 1 to 2".to_string())
+        );
+        assert!(control("0", false).is_none());
+        assert_eq!(
+            parse_command("1", &[], false),
+            Err("Nothing to pick right now".to_string())
+        );
+    }
+}
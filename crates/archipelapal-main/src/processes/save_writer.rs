@@ -0,0 +1,127 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    task::JoinHandle,
+    time::{sleep_until, Instant},
+};
+
+use crate::defs::{events::BotEvent, game_state::FullGameState};
+
+/// Writes the save file in the background. Changes are coalesced, so the save is written at most
+/// once per `interval`, except on goal and disconnect, which are written right away.
+pub fn spawn_save_writer_task(
+    game_state: Arc<FullGameState>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    let events = game_state.subscribe();
+    tokio::spawn(write_on_changes(events, interval, move || {
+        let game_state = game_state.clone();
+        async move { write_save_file(&game_state).await }
+    }))
+}
+
+/// Calls `write` whenever `events` say the save has changed, as described above
+async fn write_on_changes<F, Fut>(
+    mut events: broadcast::Receiver<BotEvent>,
+    interval: Duration,
+    mut write: F,
+) where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut dirty = false;
+    let mut next_write = Instant::now();
+
+    loop {
+        let flush = tokio::select! {
+            event = events.recv() => match event {
+                Ok(BotEvent::Goal | BotEvent::Disconnected { .. }) => true,
+                Ok(event) => {
+                    dirty |= event.changes_save();
+                    false
+                }
+                Err(RecvError::Lagged(_)) => {
+                    dirty = true;
+                    false
+                }
+                Err(RecvError::Closed) => {
+                    if dirty {
+                        write().await;
+                    }
+                    return;
+                }
+            },
+            _ = sleep_until(next_write), if dirty => true,
+        };
+
+        if flush {
+            write().await;
+            dirty = false;
+            next_write = Instant::now() + interval;
+        }
+    }
+}
+
+async fn write_save_file(game_state: &FullGameState) {
+    log::debug!("Writing save file");
+    game_state
+        .write_save_file()
+        .await
+        .inspect_err(|e| log::error!("Error saving file: {e}"))
+        .ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_debounce_and_flush() {
+        let (tx, rx) = broadcast::channel(16);
+        let writes = Arc::new(AtomicUsize::new(0));
+        let counter = writes.clone();
+        let task = tokio::spawn(write_on_changes(
+            rx,
+            Duration::from_millis(500),
+            move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async {}
+            },
+        ));
+        let settle = || tokio::time::sleep(Duration::from_millis(100));
+
+        // The first change is written straight away
+        tx.send(BotEvent::LocationChecked { location_id: 1 })
+            .unwrap();
+        settle().await;
+        assert_eq!(writes.load(Ordering::SeqCst), 1);
+
+        // The next ones wait out the interval, and are written together
+        tx.send(BotEvent::LocationUnchecked { location_id: 1 })
+            .unwrap();
+        tx.send(BotEvent::ItemIndexSynced { index: 3 }).unwrap();
+        settle().await;
+        assert_eq!(writes.load(Ordering::SeqCst), 1);
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(writes.load(Ordering::SeqCst), 2);
+
+        // Events that don't touch the save aren't written
+        tx.send(BotEvent::WaitStarted { wait_ms: 10 }).unwrap();
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        assert_eq!(writes.load(Ordering::SeqCst), 2);
+
+        // Goals are written right away, interval or not
+        tx.send(BotEvent::Goal).unwrap();
+        settle().await;
+        assert_eq!(writes.load(Ordering::SeqCst), 3);
+
+        // Anything left over is written when the bus closes
+        tx.send(BotEvent::KeyUnlocked { region: 1 }).unwrap();
+        drop(tx);
+        task.await.unwrap();
+        assert_eq!(writes.load(Ordering::SeqCst), 4);
+    }
+}